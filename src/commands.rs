@@ -1,6 +1,15 @@
 pub mod aws;
 pub mod config;
 pub mod console;
+pub mod copy;
+pub mod database;
+pub mod deploy;
+pub mod diff;
+pub mod events;
+pub mod forward;
+pub mod job;
 pub mod kubectl;
+pub mod logs;
+pub mod output;
 pub mod process;
 pub mod view;
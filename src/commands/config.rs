@@ -1,67 +1,1023 @@
+use crate::commands::output::{self, OutputFormat};
+use crate::flightctl::audit;
 use crate::flightctl::kubeclient;
 use crate::flightctl::kubeenv;
+use crate::flightctl::runner::CommandRunner;
 use crate::flightctl::{ApplicationConfig, Config, Console, Release};
+use k8s_openapi::api::core::v1 as k8s;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+use structopt::StructOpt;
 
-pub fn print(config: &Config, release: &Release) -> anyhow::Result<()> {
-    let application = config.find_application(&release)?;
+#[derive(Debug, StructOpt)]
+pub struct ConfigOptions {
+    /// Export variables as dotenv, shell or json instead of listing them
+    #[structopt(long)]
+    pub format: Option<ExportFormat>,
+
+    /// Fetch and include secret values in exported variables, recording the
+    /// access in the audit log
+    #[structopt(long, requires = "format")]
+    pub include_secrets: bool,
+
+    /// Include secrets without asking for confirmation, such as from a script
+    #[structopt(long, requires = "include-secrets")]
+    pub yes: bool,
+
+    /// Read variables from a running pod or the deployment's pod template;
+    /// defaults to a running pod, falling back to the deployment
+    #[structopt(long)]
+    pub from: Option<ConfigSource>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigSource {
+    Pod,
+    Deployment,
+}
+
+impl FromStr for ConfigSource {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<ConfigSource, String> {
+        match value {
+            "pod" => Ok(ConfigSource::Pod),
+            "deployment" => Ok(ConfigSource::Deployment),
+            _ => Err(format!(
+                "Unknown config source {} (expected pod or deployment)",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Dotenv,
+    Json,
+    Shell,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<ExportFormat, String> {
+        match value {
+            "dotenv" => Ok(ExportFormat::Dotenv),
+            "json" => Ok(ExportFormat::Json),
+            "shell" => Ok(ExportFormat::Shell),
+            _ => Err(format!(
+                "Unknown export format {} (expected dotenv, json or shell)",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct SetOptions {
+    /// Variables to set, such as RAILS_LOG_LEVEL=debug
+    #[structopt(name = "KEY=VALUE", required = true, parse(try_from_str = parse_assignment))]
+    pub vars: Vec<(String, String)>,
+
+    /// ConfigMap to add variables to when they aren't set yet. Variables which
+    /// are already set must come from this ConfigMap
+    #[structopt(long)]
+    pub config_map: Option<String>,
+
+    /// Restart the application's deployments so the change takes effect
+    #[structopt(long)]
+    pub restart: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct UnsetOptions {
+    /// Variables to remove
+    #[structopt(name = "KEY", required = true)]
+    pub names: Vec<String>,
+
+    /// Restart the application's deployments so the change takes effect
+    #[structopt(long)]
+    pub restart: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct GetOptions {
+    /// Variable to print
+    #[structopt(name = "KEY")]
+    pub name: String,
+
+    /// Print the value of a secret, recording the access in the audit log
+    #[structopt(long)]
+    pub reveal: bool,
+
+    /// Reveal without asking for confirmation, such as from a script
+    #[structopt(long, requires = "reveal")]
+    pub yes: bool,
+}
+
+pub fn parse_assignment(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("Expected KEY=VALUE, got {}", value)),
+    }
+}
+
+/// Where a variable comes from in one release, for comparing releases.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Setting {
+    pub source: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+
+    pub secret: bool,
+}
+
+impl Setting {
+    /// The kind of source, such as `configmap` or `pod`.
+    fn kind(&self) -> &str {
+        self.source.split('/').next().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Change {
+    Added,
+    Changed,
+    Removed,
+
+    /// The value is the same but comes from a differently named ConfigMap or
+    /// Secret, such as one with a generated hash suffix.
+    Renamed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VarChange {
+    pub name: String,
+    pub change: Change,
+    pub left: Option<Setting>,
+    pub right: Option<Setting>,
+}
+
+#[derive(Debug, Serialize)]
+struct Comparison {
+    left: String,
+    right: String,
+    changes: Vec<VarChange>,
+}
+
+pub fn print(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    format: OutputFormat,
+    options: &ConfigOptions,
+) -> anyhow::Result<()> {
+    with_console_env(
+        runner,
+        config,
+        release,
+        None,
+        options.from,
+        |resolver, vars| match options.format {
+            Some(export_format) => {
+                let secrets = if options.include_secrets {
+                    reveal_secrets(
+                        release,
+                        &effective_vars(&vars),
+                        options.yes,
+                        &audit::default_path()?,
+                        |secret, key| resolver.secret_value(secret, key),
+                    )?
+                } else {
+                    HashMap::new()
+                };
+                let values = export_values(vars, &secrets, options.include_secrets);
+                print!("{}", render_exports(&values, export_format)?);
+                Ok(())
+            }
+            None => output::print(format, vars.as_slice(), print_vars),
+        },
+    )
+}
+
+pub fn diff(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    left: &Release,
+    right: &Release,
+    format: OutputFormat,
+    reveal: bool,
+    yes: bool,
+) -> anyhow::Result<()> {
+    let release_settings = |release: &Release| {
+        with_console_env(runner, config, release, None, None, |resolver, vars| {
+            let secrets = if reveal {
+                reveal_secrets(
+                    release,
+                    &effective_vars(&vars),
+                    yes,
+                    &audit::default_path()?,
+                    |secret, key| resolver.secret_value(secret, key),
+                )?
+            } else {
+                HashMap::new()
+            };
+            Ok(settings(vars, &secrets))
+        })
+    };
+    let left_settings = release_settings(left)?;
+    let right_settings = release_settings(right)?;
+    let comparison = Comparison {
+        left: left.name.clone(),
+        right: right.name.clone(),
+        changes: compare(left_settings, right_settings),
+    };
+    output::print(format, &comparison, print_comparison)
+}
+
+/// Prints the value of a single variable.
+///
+/// Secret values are only printed with `--reveal`, after confirmation, and
+/// each access is recorded in the local audit log before the value is shown.
+pub fn get(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    options: &GetOptions,
+) -> anyhow::Result<()> {
+    with_console_env(runner, config, release, None, None, |resolver, vars| {
+        let var = vars
+            .iter()
+            .find(|var| !var.overridden && var.name.as_str() == options.name)
+            .ok_or(anyhow::anyhow!(
+                "{} isn't set for {}",
+                options.name,
+                release
+            ))?;
+        let source = source(&var.value);
+
+        let value = match &var.value {
+            kubeenv::ResolvedValue::SecretKeyRef { .. } => {
+                if !options.reveal {
+                    return Err(anyhow::anyhow!(
+                        "{} comes from {}; use --reveal to show it",
+                        options.name,
+                        source
+                    ));
+                }
+                reveal_secrets(
+                    release,
+                    &[var],
+                    options.yes,
+                    &audit::default_path()?,
+                    |secret, key| resolver.secret_value(secret, key),
+                )?
+                .remove(options.name.as_str())
+            }
+            kubeenv::ResolvedValue::Pod { value, .. } => Some(value.clone()),
+            kubeenv::ResolvedValue::ConfigMapKeyRef { value, .. } => {
+                value.as_ref().map(|value| value.to_string())
+            }
+            kubeenv::ResolvedValue::FieldRef { value, .. } => value.clone(),
+            kubeenv::ResolvedValue::ResourceFieldRef { value, .. } => value.clone(),
+        };
+
+        match value {
+            Some(value) => {
+                println!("{}", value);
+                Ok(())
+            }
+            None => Err(anyhow::anyhow!(
+                "Couldn't read {} from {}",
+                options.name,
+                source
+            )),
+        }
+    })
+}
+
+/// Fetches the values of secret variables once confirmed, recording each one
+/// in the audit log at `audit_log` before it's returned. Every command which
+/// shows secret values reveals them through here.
+pub fn reveal_secrets<F>(
+    release: &Release,
+    vars: &[&kubeenv::ResolvedVar],
+    yes: bool,
+    audit_log: &Path,
+    mut lookup: F,
+) -> anyhow::Result<HashMap<String, String>>
+where
+    F: FnMut(&str, &str) -> Option<Rc<String>>,
+{
+    let secrets: Vec<(&kubeenv::ResolvedVar, &str, &str)> = vars
+        .iter()
+        .filter_map(|var| match &var.value {
+            kubeenv::ResolvedValue::SecretKeyRef { secret, key } => {
+                Some((*var, secret.as_str(), key.as_str()))
+            }
+            _ => None,
+        })
+        .collect();
+    let description = match secrets.as_slice() {
+        [] => return Ok(HashMap::new()),
+        [(var, _, _)] => source(&var.value),
+        secrets => format!("{} secrets", secrets.len()),
+    };
+    confirm_reveal(&description, release, yes)?;
+
+    let mut values = HashMap::new();
+    for (var, secret, key) in secrets {
+        audit::record(
+            audit_log,
+            &audit::Entry::new(
+                &release.name,
+                &release.context,
+                &var.name,
+                &source(&var.value),
+            ),
+        )?;
+        if let Some(value) = lookup(secret, key) {
+            values.insert(var.name.to_string(), value.to_string());
+        }
+    }
+    Ok(values)
+}
+
+/// Skips definitions which the kubelet never injects because a later one
+/// replaces them.
+fn effective_vars(vars: &[kubeenv::ResolvedVar]) -> Vec<&kubeenv::ResolvedVar> {
+    vars.iter().filter(|var| !var.overridden).collect()
+}
+
+fn confirm_reveal(source: &str, release: &Release, yes: bool) -> anyhow::Result<()> {
+    if yes {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!(
+            "Refusing to reveal {} without a terminal to confirm; pass --yes to reveal it anyway",
+            source
+        ));
+    }
+
+    eprint!("Reveal {} for {}? [y/N] ", source, release);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    match answer.trim() {
+        "y" | "Y" | "yes" => Ok(()),
+        _ => Err(anyhow::anyhow!("Not revealing {}", source)),
+    }
+}
+
+/// Sets variables in the ConfigMaps which provide them.
+pub fn set(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    options: &SetOptions,
+) -> anyhow::Result<()> {
+    let changes = options
+        .vars
+        .iter()
+        .map(|(name, value)| (name.clone(), Some(value.clone())))
+        .collect();
+    update(
+        runner,
+        config,
+        release,
+        changes,
+        options.config_map.as_deref(),
+        options.restart,
+    )
+}
+
+/// Removes variables from the ConfigMaps which provide them.
+pub fn unset(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    options: &UnsetOptions,
+) -> anyhow::Result<()> {
+    let changes = options
+        .names
+        .iter()
+        .map(|name| (name.clone(), None))
+        .collect();
+    update(runner, config, release, changes, None, options.restart)
+}
+
+fn update(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    changes: Vec<(String, Option<String>)>,
+    new_config_map: Option<&str>,
+    restart: bool,
+) -> anyhow::Result<()> {
+    let patches = with_console_container(
+        runner,
+        config,
+        release,
+        None,
+        None,
+        |resolver, pod, container| {
+            let vars = resolver.resolve(pod, container.clone());
+            config_patches(container, &vars, changes, new_config_map, release)
+        },
+    )?;
+
+    let client = kubeclient::new(runner, &release.context);
+    apply_patches(&client, &patches)?;
+
+    if restart {
+        restart_workloads(runner, config, release)
+    } else {
+        log::info!(
+            "Running pods keep their current values until restarted; use --restart to restart them"
+        );
+        Ok(())
+    }
+}
+
+/// Changes to make to each ConfigMap, by name, mapping keys to new values,
+/// or to null to remove them.
+pub type ConfigPatches = BTreeMap<String, serde_json::Map<String, serde_json::Value>>;
+
+/// Works out which ConfigMap keys to change to set or unset variables for a
+/// container. Variables can only be removed when the container would still
+/// start without them, and new variables can only be added to ConfigMaps
+/// which the container imports with `envFrom`.
+pub fn config_patches(
+    container: &k8s::Container,
+    vars: &[kubeenv::ResolvedVar],
+    changes: Vec<(String, Option<String>)>,
+    new_config_map: Option<&str>,
+    release: &Release,
+) -> anyhow::Result<ConfigPatches> {
+    let mut patches = ConfigPatches::new();
+
+    for (name, value) in changes {
+        let var = vars
+            .iter()
+            .find(|var| !var.overridden && var.name.as_str() == name);
+        let (config_map, key) = match (var.map(|var| &var.value), new_config_map, &value) {
+            (Some(value @ kubeenv::ResolvedValue::ConfigMapKeyRef { .. }), _, None)
+                if is_required(container, &name) =>
+            {
+                return Err(anyhow::anyhow!(
+                    "{} is required by container {} through valueFrom {}, so new pods \
+                     couldn't start without it; make the reference optional or remove it \
+                     from the manifests instead",
+                    name,
+                    container.name,
+                    source(value)
+                ))
+            }
+            (
+                Some(
+                    value @ kubeenv::ResolvedValue::ConfigMapKeyRef {
+                        config_map: current,
+                        ..
+                    },
+                ),
+                Some(config_map),
+                _,
+            ) if current.as_str() != config_map => {
+                return Err(anyhow::anyhow!(
+                    "{} is already set from {}, not configmap/{}; unset it first to move it",
+                    name,
+                    source(value),
+                    config_map
+                ))
+            }
+            (
+                Some(kubeenv::ResolvedValue::ConfigMapKeyRef {
+                    config_map, key, ..
+                }),
+                _,
+                _,
+            ) => (config_map.to_string(), key.to_string()),
+            (Some(value), _, _) => {
+                return Err(anyhow::anyhow!(
+                    "{} is set from {}, not a ConfigMap; change it in the manifests instead",
+                    name,
+                    source(value)
+                ))
+            }
+            (None, Some(config_map), Some(_)) => {
+                if !imported_config_maps(container).contains(&config_map) {
+                    return Err(anyhow::anyhow!(
+                        "configmap/{} isn't imported with envFrom by container {}, so {} \
+                         wouldn't reach it",
+                        config_map,
+                        container.name,
+                        name
+                    ));
+                }
+                (config_map.to_string(), name.clone())
+            }
+            (None, None, Some(_)) => {
+                return Err(anyhow::anyhow!(
+                    "{} isn't set for {}; use --config-map to choose where to add it",
+                    name,
+                    release
+                ))
+            }
+            (None, _, None) => return Err(anyhow::anyhow!("{} isn't set for {}", name, release)),
+        };
+
+        patches.entry(config_map).or_default().insert(
+            key,
+            value
+                .map(serde_json::Value::String)
+                .unwrap_or(serde_json::Value::Null),
+        );
+    }
+
+    Ok(patches)
+}
+
+/// Patches each ConfigMap with its changed keys.
+pub fn apply_patches(
+    client: &kubeclient::KubeClient,
+    patches: &ConfigPatches,
+) -> anyhow::Result<()> {
+    for (config_map, data) in patches {
+        log::info!("Updating configmap/{}", config_map);
+        client.patch(
+            &format!("configmap/{}", config_map),
+            &serde_json::json!({ "data": data }),
+        )?;
+    }
+    Ok(())
+}
+
+/// Whether the container's effective definition of a variable is a
+/// non-optional `valueFrom.configMapKeyRef`, which stops pods from starting
+/// when the key is missing. Definitions in `env` replace those imported with
+/// `envFrom`, so the last one wins.
+fn is_required(container: &k8s::Container, name: &str) -> bool {
+    container
+        .env
+        .iter()
+        .flatten()
+        .rev()
+        .find(|var| var.name == name)
+        .and_then(|var| var.value_from.as_ref())
+        .and_then(|value_from| value_from.config_map_key_ref.as_ref())
+        .is_some_and(|key_ref| key_ref.optional != Some(true))
+}
+
+fn imported_config_maps(container: &k8s::Container) -> Vec<&str> {
+    container
+        .env_from
+        .iter()
+        .flatten()
+        .filter_map(|source| source.config_map_ref.as_ref())
+        .filter_map(|config_map| config_map.name.as_deref())
+        .collect()
+}
+
+fn restart_workloads(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
 
     match &application.config {
-        ApplicationConfig::Kubectl { console, selector } => {
-            let client = kubeclient::new(&release.context);
+        ApplicationConfig::Kubectl { selector, .. } => {
+            let client = kubeclient::new(runner, &release.context);
+            let workloads = client.get_workloads(kubeclient::Selector::new(selector.clone()))?;
+            for deployment in workloads {
+                let resource = format!(
+                    "deployment/{}",
+                    deployment.metadata.name.unwrap_or_default()
+                );
+                log::info!("Restarting {}", resource);
+                client.rollout_restart(&resource)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Resolves the environment of a console's container, from a running pod or
+/// the deployment's pod template, and passes it to `f`. Without a name, the
+/// application's default console is used.
+pub(crate) fn with_console_env<T, F>(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    console: Option<&str>,
+    source: Option<ConfigSource>,
+    f: F,
+) -> anyhow::Result<T>
+where
+    F: FnOnce(&mut kubeenv::Resolver, Vec<kubeenv::ResolvedVar>) -> anyhow::Result<T>,
+{
+    with_console_container(
+        runner,
+        config,
+        release,
+        console,
+        source,
+        |resolver, pod, container| {
+            let vars = resolver.resolve(pod, container.clone());
+            f(resolver, vars)
+        },
+    )
+}
+
+/// Finds a console's container, from a running pod or the deployment's pod
+/// template, and passes it to `f` with a resolver for its environment.
+fn with_console_container<T, F>(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    console: Option<&str>,
+    source: Option<ConfigSource>,
+    f: F,
+) -> anyhow::Result<T>
+where
+    F: FnOnce(&mut kubeenv::Resolver, &k8s::Pod, &k8s::Container) -> anyhow::Result<T>,
+{
+    let application = config.find_application(release)?;
+
+    match &application.config {
+        ApplicationConfig::Kubectl { selector, .. } => {
+            let client = kubeclient::new(runner, &release.context);
             let base_selector = kubeclient::Selector::new(selector.clone());
 
-            match console {
-                Some(Console::Exec {
+            match application.find_console(console)? {
+                Console::Exec {
+                    container,
+                    selector,
+                    ..
+                }
+                | Console::Run {
+                    container,
+                    selector,
+                    ..
+                }
+                | Console::Debug {
                     container,
                     selector,
                     ..
-                }) => {
+                } => {
                     let console_selector =
                         base_selector.extend(&kubeclient::Selector::new(selector.clone()));
-                    let pod = client.get_available_pod(console_selector)?;
+                    let pod = match source {
+                        Some(ConfigSource::Pod) => client.get_available_pod(console_selector)?,
+                        Some(ConfigSource::Deployment) => {
+                            client
+                                .get_pod_template(&base_selector, &console_selector)?
+                                .1
+                        }
+                        None => match client.find_available_pod(&console_selector)? {
+                            Some(pod) => pod,
+                            None => {
+                                let (name, pod) =
+                                    client.get_pod_template(&base_selector, &console_selector)?;
+                                log::info!(
+                                    "No running pod found; reading configuration from deployment/{}",
+                                    name
+                                );
+                                pod
+                            }
+                        },
+                    };
                     let container = pod
                         .spec
-                        .map(|spec| spec.containers.into_iter().find(|c| &c.name == container))
-                        .flatten()
+                        .as_ref()
+                        .and_then(|spec| spec.containers.iter().find(|c| &c.name == container))
+                        .cloned()
                         .ok_or(anyhow::anyhow!("Couldn't find container {}", container))?;
                     let mut resolver = kubeenv::Resolver::new(&client);
-                    let config = resolver.resolve(container);
-                    for var in config {
-                        match var.value {
-                            kubeenv::ResolvedValue::Pod { value } => {
-                                println!("{}: {} (from pod)", &var.name, &show_value(value))
-                            }
-                            kubeenv::ResolvedValue::ConfigMapKeyRef {
-                                config_map,
-                                key,
-                                value,
-                            } => println!(
-                                "{}: {} (from configmap/{}.{})",
-                                &var.name,
-                                &show_value(value.map(|s| s.to_string())),
-                                config_map,
-                                key
-                            ),
-                            kubeenv::ResolvedValue::SecretKeyRef { secret, key } => println!(
-                                "{}: ******************** (from secret/{}.{})",
-                                &var.name, secret, key
-                            ),
-                            kubeenv::ResolvedValue::FieldRef { path } => {
-                                println!("{}: (reference to {})", &var.name, &path)
-                            }
-                        }
-                    }
-                    Ok(())
+                    f(&mut resolver, &pod, &container)
+                }
+            }
+        }
+    }
+}
+
+fn print_vars(vars: &[kubeenv::ResolvedVar]) {
+    for var in vars {
+        let description = match &var.value {
+            kubeenv::ResolvedValue::Pod {
+                value,
+                template: Some(template),
+            } => format!("{} (from pod, expanded from {})", value, template),
+            kubeenv::ResolvedValue::Pod {
+                value,
+                template: None,
+            } => format!("{} (from pod)", value),
+            kubeenv::ResolvedValue::ConfigMapKeyRef {
+                config_map,
+                key,
+                value,
+            } => format!(
+                "{} (from configmap/{}.{})",
+                &show_value(value.as_deref().map(|s| s.as_str())),
+                config_map,
+                key
+            ),
+            kubeenv::ResolvedValue::SecretKeyRef { secret, key } => {
+                format!("******************** (from secret/{}.{})", secret, key)
+            }
+            kubeenv::ResolvedValue::FieldRef { path, value } => {
+                format!("{} (reference to {})", &show_value(value.as_deref()), path)
+            }
+            kubeenv::ResolvedValue::ResourceFieldRef { resource, value } => format!(
+                "{} (reference to {})",
+                value.as_deref().unwrap_or("(node allocatable)"),
+                resource
+            ),
+        };
+
+        if var.overridden {
+            println!("{}: {} [overridden]", &var.name, description);
+        } else {
+            println!("{}: {}", &var.name, description);
+        }
+    }
+}
+
+fn show_value(value: Option<&str>) -> &str {
+    value.unwrap_or("(unset)")
+}
+
+/// Lists the values of variables to export, using the values of revealed
+/// `secrets`; secrets are omitted unless `include_secrets` is set.
+pub fn export_values(
+    vars: Vec<kubeenv::ResolvedVar>,
+    secrets: &HashMap<String, String>,
+    include_secrets: bool,
+) -> Vec<(String, String)> {
+    let mut values = Vec::new();
+    let mut omitted_secrets = 0;
+
+    for var in vars.into_iter().filter(|var| !var.overridden) {
+        let value = match var.value {
+            kubeenv::ResolvedValue::Pod { value, .. } => Some(value),
+            kubeenv::ResolvedValue::ConfigMapKeyRef { value, .. } => {
+                value.map(|value| value.to_string())
+            }
+            kubeenv::ResolvedValue::SecretKeyRef { .. } => {
+                if include_secrets {
+                    secrets.get(var.name.as_str()).cloned()
+                } else {
+                    omitted_secrets += 1;
+                    None
+                }
+            }
+            kubeenv::ResolvedValue::FieldRef { path, value } => {
+                if value.is_none() {
+                    log::warn!("Skipping {}, which refers to {}", &var.name, &path);
                 }
-                None => Err(anyhow::Error::msg(format!(
-                    "No console configured for application: {}",
-                    application.name
-                ))),
+                value
             }
+            kubeenv::ResolvedValue::ResourceFieldRef { resource, value } => {
+                if value.is_none() {
+                    log::warn!("Skipping {}, which refers to {}", &var.name, &resource);
+                }
+                value
+            }
+        };
+
+        if let Some(value) = value {
+            values.push((var.name.to_string(), value));
+        }
+    }
+
+    if omitted_secrets > 0 {
+        log::info!(
+            "Omitted {} secret variables; use --include-secrets to export them",
+            omitted_secrets
+        );
+    }
+
+    values
+}
+
+/// Renders variables in an export format, quoting values as needed.
+pub fn render_exports(values: &[(String, String)], format: ExportFormat) -> anyhow::Result<String> {
+    match format {
+        ExportFormat::Dotenv => Ok(values
+            .iter()
+            .map(|(name, value)| format!("{}={}\n", name, dotenv_quote(value)))
+            .collect()),
+        ExportFormat::Json => {
+            let object: BTreeMap<&str, &str> = values
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            Ok(format!("{}\n", serde_json::to_string_pretty(&object)?))
+        }
+        ExportFormat::Shell => Ok(values
+            .iter()
+            .map(|(name, value)| format!("export {}={}\n", name, shell_quote(value)))
+            .collect()),
+    }
+}
+
+fn is_bare(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:@%+,".contains(c))
+}
+
+/// Quotes a value for dotenv files. Single quotes are literal in every
+/// dotenv implementation, so they're preferred; values which can't be
+/// single-quoted use double quotes with escapes, including `$` to prevent
+/// interpolation.
+fn dotenv_quote(value: &str) -> String {
+    if is_bare(value) {
+        String::from(value)
+    } else if !value.contains('\'') && !value.contains('\n') {
+        format!("'{}'", value)
+    } else {
+        let escaped = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('$', "\\$")
+            .replace('\n', "\\n");
+        format!("\"{}\"", escaped)
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    if is_bare(value) {
+        String::from(value)
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Describes each variable for comparison, using the values of revealed
+/// `secrets`.
+pub fn settings(
+    vars: Vec<kubeenv::ResolvedVar>,
+    secrets: &HashMap<String, String>,
+) -> BTreeMap<String, Setting> {
+    let mut settings = BTreeMap::new();
+
+    for var in vars.into_iter().filter(|var| !var.overridden) {
+        let source = source(&var.value);
+        let setting = match var.value {
+            kubeenv::ResolvedValue::Pod { value, .. } => Setting {
+                source,
+                value: Some(value),
+                secret: false,
+            },
+            kubeenv::ResolvedValue::ConfigMapKeyRef { value, .. } => Setting {
+                source,
+                value: value.map(|value| value.to_string()),
+                secret: false,
+            },
+            kubeenv::ResolvedValue::SecretKeyRef { .. } => Setting {
+                source,
+                value: secrets.get(var.name.as_str()).cloned(),
+                secret: true,
+            },
+            kubeenv::ResolvedValue::FieldRef { path, value } => Setting {
+                source,
+                value: Some(format!(
+                    "{} (reference to {})",
+                    show_value(value.as_deref()),
+                    path
+                )),
+                secret: false,
+            },
+            kubeenv::ResolvedValue::ResourceFieldRef { resource, value } => Setting {
+                source,
+                value: Some(format!(
+                    "{} (reference to {})",
+                    value.as_deref().unwrap_or("(node allocatable)"),
+                    resource
+                )),
+                secret: false,
+            },
+        };
+        settings.insert(var.name.to_string(), setting);
+    }
+
+    settings
+}
+
+/// Describes where a variable comes from, such as `configmap/web.PORT`.
+pub(crate) fn source(value: &kubeenv::ResolvedValue) -> String {
+    match value {
+        kubeenv::ResolvedValue::Pod { .. } => String::from("pod"),
+        kubeenv::ResolvedValue::ConfigMapKeyRef {
+            config_map, key, ..
+        } => format!("configmap/{}.{}", config_map, key),
+        kubeenv::ResolvedValue::SecretKeyRef { secret, key } => {
+            format!("secret/{}.{}", secret, key)
+        }
+        kubeenv::ResolvedValue::FieldRef { path, .. } => format!("field/{}", path),
+        kubeenv::ResolvedValue::ResourceFieldRef { resource, .. } => {
+            format!("resource/{}", resource)
         }
     }
 }
 
-fn show_value(value: Option<String>) -> String {
-    value.unwrap_or(String::from("(unset)"))
+/// Finds the variables which differ between two releases. Values from
+/// ConfigMaps or Secrets with different names are only renamed when the
+/// values match, since names often differ between environments or include
+/// generated hashes.
+pub fn compare(
+    mut left: BTreeMap<String, Setting>,
+    mut right: BTreeMap<String, Setting>,
+) -> Vec<VarChange> {
+    let mut names: Vec<String> = left.keys().chain(right.keys()).cloned().collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let left_setting = left.remove(&name);
+            let right_setting = right.remove(&name);
+            let change = match (&left_setting, &right_setting) {
+                (Some(_), None) => Change::Removed,
+                (None, Some(_)) => Change::Added,
+                (Some(l), Some(r)) if l == r => return None,
+                (Some(l), Some(r))
+                    if l.kind() == r.kind()
+                        && matches!(l.kind(), "configmap" | "secret")
+                        && l.value.is_some()
+                        && l.value == r.value =>
+                {
+                    Change::Renamed
+                }
+                (Some(_), Some(_)) => Change::Changed,
+                (None, None) => return None,
+            };
+            Some(VarChange {
+                name,
+                change,
+                left: left_setting,
+                right: right_setting,
+            })
+        })
+        .collect()
+}
+
+fn print_comparison(comparison: &Comparison) {
+    if comparison.changes.is_empty() {
+        log::info!(
+            "No differences between {} and {}",
+            comparison.left,
+            comparison.right
+        );
+        return;
+    }
+
+    println!("--- {}", comparison.left);
+    println!("+++ {}", comparison.right);
+    for line in comparison.changes.iter().filter_map(show_change) {
+        println!("{}", line);
+    }
+}
+
+/// Formats a change like a diff line, with renamed sources marked `=` since
+/// their values match.
+pub fn show_change(var: &VarChange) -> Option<String> {
+    match (&var.left, &var.right) {
+        (Some(left), None) => Some(format!("- {}: {}", var.name, describe(left))),
+        (None, Some(right)) => Some(format!("+ {}: {}", var.name, describe(right))),
+        (Some(left), Some(right)) if var.change == Change::Renamed => Some(format!(
+            "= {}: {} (from {} -> {})",
+            var.name,
+            show_value(left.value.as_deref()),
+            left.source,
+            right.source
+        )),
+        (Some(left), Some(right)) => Some(format!(
+            "~ {}: {} -> {}",
+            var.name,
+            describe(left),
+            describe(right)
+        )),
+        (None, None) => None,
+    }
+}
+
+fn describe(setting: &Setting) -> String {
+    let value = match (&setting.value, setting.secret) {
+        (Some(value), _) => value.as_str(),
+        (None, true) => "********************",
+        (None, false) => "(unset)",
+    };
+    format!("{} (from {})", value, setting.source)
 }
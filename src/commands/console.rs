@@ -1,44 +1,263 @@
-use crate::flightctl::kubeclient;
-use crate::flightctl::{ApplicationConfig, Config, Console, Release};
+use crate::flightctl::interrupt::Interrupts;
+use crate::flightctl::kubeclient::{self, KubeClient};
+use crate::flightctl::kubectl;
+use crate::flightctl::podspec;
+use crate::flightctl::runner::CommandRunner;
+use crate::flightctl::{Application, ApplicationConfig, Config, Console, Release};
+use k8s_openapi::api::core::v1 as k8s;
+use std::fs::File;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
 
-pub fn run_default(config: &Config, release: &Release) -> anyhow::Result<()> {
-    let application = config.find_application(&release)?;
+/// How long to wait for an ephemeral console pod to start.
+pub(crate) const POD_START_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 
-    match &application.config {
-        ApplicationConfig::Kubectl { console, .. } => match console {
-            Some(Console::Exec { command, .. }) => run_command(config, release, command),
-            None => Err(anyhow::Error::msg(format!(
-                "No console configured for application: {}",
-                application.name
-            ))),
-        },
+#[derive(Debug, StructOpt)]
+pub struct TtyOptions {
+    /// Allocate a TTY even when stdin or stdout isn't a terminal
+    #[structopt(long, overrides_with = "no-tty")]
+    pub tty: bool,
+
+    /// Don't allocate a TTY, such as when piping input to a command
+    #[structopt(long, overrides_with = "tty")]
+    pub no_tty: bool,
+}
+
+impl TtyOptions {
+    /// Whether to allocate a TTY: as requested, or when both stdin and stdout
+    /// are terminals.
+    pub fn enabled(&self) -> bool {
+        if self.tty {
+            true
+        } else if self.no_tty {
+            false
+        } else {
+            io::stdin().is_terminal() && io::stdout().is_terminal()
+        }
+    }
+}
+
+/// Opens a console for a release, choosing a named console or the
+/// application's default.
+pub fn run(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    name: Option<&str>,
+    tty: bool,
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
+
+    let console = application.find_console(name)?;
+    let command = match console {
+        Console::Exec { command, .. }
+        | Console::Run { command, .. }
+        | Console::Debug { command, .. } => command,
+    };
+    run_in_console(runner, application, release, console, command, tty)
+}
+
+pub fn run_command(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    cmd: &Vec<String>,
+    tty: bool,
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
+    let console = application.find_console(None)?;
+    run_in_console(runner, application, release, console, cmd, tty)
+}
+
+/// Uploads a local script into the default console's container and runs it
+/// with `interpreter`, such as `rails runner`, removing it afterwards.
+pub fn run_script(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    script: &Path,
+    interpreter: &[String],
+    tty: bool,
+) -> anyhow::Result<()> {
+    if interpreter.is_empty() {
+        return Err(anyhow::Error::msg(
+            "Specify a command to run the script with, such as -- rails runner",
+        ));
+    }
+    let mut file = File::open(script)
+        .map_err(|err| anyhow::anyhow!("Couldn't open {}: {}", script.display(), err))?;
+    let application = config.find_application(release)?;
+
+    match (&application.config, application.find_console(None)?) {
+        (
+            ApplicationConfig::Kubectl { selector, .. },
+            Console::Exec {
+                container,
+                selector: console_selector,
+                ..
+            },
+        ) => {
+            let client = kubeclient::new(runner, &release.context);
+            let pod_selector = kubeclient::Selector::new(selector.clone())
+                .extend(&kubeclient::Selector::new(console_selector.clone()));
+            let pod = client.get_available_pod(pod_selector)?;
+            let remote_path = remote_script_path(script);
+            exec_script(
+                &client,
+                &pod,
+                container,
+                &remote_path,
+                &mut file,
+                interpreter,
+                tty,
+            )
+        }
+        _ => Err(anyhow::anyhow!(
+            "Scripts can only be run in exec consoles, but {}'s console isn't one",
+            application.name
+        )),
     }
 }
 
-pub fn run_command(config: &Config, release: &Release, cmd: &Vec<String>) -> anyhow::Result<()> {
-    let application = config.find_application(&release)?;
+/// Uploads a script to `remote_path` in a running container, runs it with
+/// `interpreter`, and removes it, even when the script fails.
+pub fn exec_script(
+    client: &KubeClient,
+    pod: &k8s::Pod,
+    container: &str,
+    remote_path: &str,
+    script: &mut (dyn io::Read + Send),
+    interpreter: &[String],
+    tty: bool,
+) -> anyhow::Result<()> {
+    log::info!("Uploading script to {}", remote_path);
+    client.upload(pod, container, remote_path, script)?;
+
+    let command: Vec<&str> = interpreter
+        .iter()
+        .map(|arg| arg.as_str())
+        .chain([remote_path])
+        .collect();
+    let result = client.exec(pod, container, &command, tty);
 
+    log::debug!("Removing {}", remote_path);
+    let removed = client.remove_file(pod, container, remote_path);
+    result.and(removed)
+}
+
+/// Picks a unique path for a script in the container's temporary directory,
+/// keeping its extension for interpreters which care.
+fn remote_script_path(script: &Path) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.subsec_nanos())
+        .unwrap_or_default();
+    let extension = script
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    format!(
+        "/tmp/flightctl-script-{}-{}{}",
+        std::process::id(),
+        nanos,
+        extension
+    )
+}
+
+/// Creates a pod, attaches to a container until it exits, and deletes the
+/// pod afterwards, even when starting or attaching fails or is interrupted.
+/// Containers which finish before they can be attached to show their logs
+/// instead.
+pub fn run_ephemeral(
+    client: &KubeClient,
+    pod: &k8s::Pod,
+    container: &str,
+    tty: bool,
+) -> anyhow::Result<()> {
+    let _interrupts = Interrupts::catch()?;
+    let manifest = kubectl::write_temp(&serde_json::to_string(pod)?)?;
+    let name = client
+        .create(&manifest)?
+        .into_iter()
+        .next()
+        .ok_or(anyhow::Error::msg("kubectl didn't report the created pod"))?;
+    manifest.close()?;
+
+    log::info!("Waiting for {} to start", name);
+    let result = client
+        .wait_for_start(&name, container, POD_START_TIMEOUT)
+        .and_then(|running| {
+            if running {
+                client.attach(&name, container, tty)
+            } else {
+                log::info!("{} already finished; showing its logs", name);
+                client.follow_logs(&name, container, POD_START_TIMEOUT)
+            }
+        });
+
+    log::info!("Deleting {}", name);
+    let deleted = client.delete(&name);
+    result.and(deleted)
+}
+
+fn run_in_console(
+    runner: &dyn CommandRunner,
+    application: &Application,
+    release: &Release,
+    console: &Console,
+    cmd: &Vec<String>,
+    tty: bool,
+) -> anyhow::Result<()> {
     match &application.config {
-        ApplicationConfig::Kubectl { console, selector } => {
-            let client = kubeclient::new(&release.context);
+        ApplicationConfig::Kubectl { selector, .. } => {
+            let client = kubeclient::new(runner, &release.context);
             let base_selector = kubeclient::Selector::new(selector.clone());
 
             match console {
-                Some(Console::Exec {
+                Console::Exec {
                     container,
                     selector,
                     ..
-                }) => {
+                } => {
                     let console_selector =
                         base_selector.extend(&kubeclient::Selector::new(selector.clone()));
                     let pod = client.get_available_pod(console_selector)?;
-                    client.exec(&pod, container, cmd)?;
+                    client.exec(&pod, container, cmd, tty)?;
                     Ok(())
                 }
-                None => Err(anyhow::Error::msg(format!(
-                    "No console configured for application: {}",
-                    application.name
-                ))),
+                Console::Run {
+                    container,
+                    selector,
+                    resources,
+                    ..
+                } => {
+                    let console_selector =
+                        base_selector.extend(&kubeclient::Selector::new(selector.clone()));
+                    let (deployment, template) =
+                        client.get_pod_template(&base_selector, &console_selector)?;
+                    let pod = podspec::console_pod(
+                        template,
+                        &deployment,
+                        container,
+                        cmd,
+                        resources.as_ref(),
+                        tty,
+                    )?;
+                    run_ephemeral(&client, &pod, container, tty)
+                }
+                Console::Debug {
+                    container,
+                    image,
+                    selector,
+                    ..
+                } => {
+                    let console_selector =
+                        base_selector.extend(&kubeclient::Selector::new(selector.clone()));
+                    let pod = client.get_available_pod(console_selector)?;
+                    client.debug(&pod, container, image, cmd, tty)
+                }
             }
         }
     }
@@ -0,0 +1,286 @@
+use crate::flightctl::kubeclient::{self, KubeClient};
+use crate::flightctl::runner::CommandRunner;
+use crate::flightctl::transfer::{self, Progress};
+use crate::flightctl::{ApplicationConfig, Config, Console, Release};
+use k8s_openapi::api::core::v1 as k8s;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use tempfile::NamedTempFile;
+
+const REMOTE_STAT: &str = "if [ -d \"$0\" ]; then echo directory; \
+    elif [ -f \"$0\" ]; then echo file $(wc -c < \"$0\"); else echo missing; fi";
+const REMOTE_WRITE_FILE: &str = "mkdir -p \"$(dirname \"$0\")\" && cat > \"$0\"";
+const REMOTE_UNPACK: &str = "mkdir -p \"$0\" && tar xf - -C \"$0\"";
+const REMOTE_FILE_CHECKSUM: &str = "sha256sum \"$0\"";
+const REMOTE_DIR_CHECKSUMS: &str = "cd \"$0\" && find . -type f -exec sha256sum {} +";
+
+#[derive(Debug, StructOpt)]
+pub struct CopyOptions {
+    /// File or directory to copy. Prefix paths in the console container
+    /// with a colon, such as :/tmp/export.csv
+    pub source: String,
+
+    /// Where to copy to, such as ./export.csv, or :/tmp/ to copy into a
+    /// directory in the console container
+    pub destination: String,
+
+    /// Console whose container to copy to or from, as listed by
+    /// `view consoles`
+    #[structopt(long)]
+    pub console: Option<String>,
+
+    /// Don't compare checksums after copying
+    #[structopt(long)]
+    pub no_verify: bool,
+}
+
+pub fn run(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    options: &CopyOptions,
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
+
+    match (
+        &application.config,
+        application.find_console(options.console.as_deref())?,
+    ) {
+        (
+            ApplicationConfig::Kubectl { selector, .. },
+            Console::Exec {
+                container,
+                selector: console_selector,
+                ..
+            },
+        ) => {
+            let client = kubeclient::new(runner, &release.context);
+            let pod_selector = kubeclient::Selector::new(selector.clone())
+                .extend(&kubeclient::Selector::new(console_selector.clone()));
+            let pod = client.get_available_pod(pod_selector)?;
+            let verify = !options.no_verify;
+
+            match (
+                options.source.strip_prefix(':'),
+                options.destination.strip_prefix(':'),
+            ) {
+                (None, Some(remote)) => {
+                    let local = Path::new(&options.source);
+                    upload(runner, &client, &pod, container, local, remote, verify)
+                }
+                (Some(remote), None) => {
+                    let local = Path::new(&options.destination);
+                    download(runner, &client, &pod, container, remote, local, verify)
+                }
+                _ => Err(anyhow::Error::msg(
+                    "Exactly one path must be in the console container, such as :/tmp/export.csv",
+                )),
+            }
+        }
+        _ => Err(anyhow::anyhow!(
+            "Files can only be copied with exec consoles, but that console for {} isn't one",
+            application.name
+        )),
+    }
+}
+
+/// Copies a local file or directory into a container. A destination ending
+/// in `/` is a directory to copy into.
+pub fn upload(
+    runner: &dyn CommandRunner,
+    client: &KubeClient,
+    pod: &k8s::Pod,
+    container: &str,
+    local: &Path,
+    remote: &str,
+    verify: bool,
+) -> anyhow::Result<()> {
+    let metadata = fs::metadata(local)
+        .map_err(|err| anyhow::anyhow!("Couldn't read {}: {}", local.display(), err))?;
+    let absolute = local.canonicalize()?;
+    let name = file_name(&absolute.to_string_lossy())?.to_string();
+    let remote = if remote.ends_with('/') {
+        format!("{}{}", remote, name)
+    } else {
+        String::from(remote)
+    };
+    let label = format!("Uploading {}", name);
+
+    if metadata.is_dir() {
+        let archive = NamedTempFile::new()?;
+        run_tar(
+            runner,
+            &[
+                "cf",
+                &archive.path().to_string_lossy(),
+                "-C",
+                &local.to_string_lossy(),
+                ".",
+            ],
+        )?;
+        let size = archive.as_file().metadata()?.len();
+        let mut progress = Progress::new(File::open(archive.path())?, &label, Some(size));
+        client.exec_input(
+            pod,
+            container,
+            &["sh", "-c", REMOTE_UNPACK, &remote],
+            &mut progress,
+        )?;
+        progress.finish();
+    } else {
+        let mut progress = Progress::new(File::open(local)?, &label, Some(metadata.len()));
+        client.exec_input(
+            pod,
+            container,
+            &["sh", "-c", REMOTE_WRITE_FILE, &remote],
+            &mut progress,
+        )?;
+        progress.finish();
+    }
+
+    if verify {
+        let expected = transfer::local_checksums(local)?;
+        let actual = remote_checksums(client, pod, container, &remote, metadata.is_dir());
+        verify_checksums(Some(expected), actual)?;
+    }
+    log::info!("Copied {} to :{}", local.display(), remote);
+    Ok(())
+}
+
+/// Copies a file or directory out of a container. A destination which is a
+/// directory, or ends in `/`, is a directory to copy into.
+pub fn download(
+    runner: &dyn CommandRunner,
+    client: &KubeClient,
+    pod: &k8s::Pod,
+    container: &str,
+    remote: &str,
+    local: &Path,
+    verify: bool,
+) -> anyhow::Result<()> {
+    let remote = match remote.trim_end_matches('/') {
+        "" => "/",
+        remote => remote,
+    };
+    let name = file_name(remote)?;
+    let local: PathBuf = if local.is_dir() || local.to_string_lossy().ends_with('/') {
+        local.join(name)
+    } else {
+        local.to_path_buf()
+    };
+    let parent = match local.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    fs::create_dir_all(&parent)?;
+    let label = format!("Downloading {}", name);
+
+    let stat = client.exec_output(pod, container, &["sh", "-c", REMOTE_STAT, remote])?;
+    let stat = String::from_utf8_lossy(&stat.stdout);
+    let (is_dir, size) = match stat.split_whitespace().collect::<Vec<&str>>().as_slice() {
+        ["directory"] => (true, None),
+        ["file", size] => (false, size.parse().ok()),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Couldn't find :{} in {}",
+                remote,
+                container
+            ))
+        }
+    };
+
+    if !is_dir {
+        let mut progress = Progress::new(NamedTempFile::new_in(&parent)?, &label, size);
+        client.exec_to(pod, container, &["cat", remote], &mut progress)?;
+        progress.finish().persist(&local)?;
+    } else {
+        let mut progress = Progress::new(NamedTempFile::new_in(&parent)?, &label, None);
+        client.exec_to(
+            pod,
+            container,
+            &["tar", "cf", "-", "-C", remote, "."],
+            &mut progress,
+        )?;
+        let archive = progress.finish();
+        fs::create_dir_all(&local)?;
+        run_tar(
+            runner,
+            &[
+                "xf",
+                &archive.path().to_string_lossy(),
+                "-C",
+                &local.to_string_lossy(),
+            ],
+        )?;
+    }
+
+    if verify {
+        let expected = remote_checksums(client, pod, container, remote, is_dir);
+        let actual = transfer::local_checksums(&local)?;
+        verify_checksums(expected, Some(actual))?;
+    }
+    log::info!("Copied :{} to {}", remote, local.display());
+    Ok(())
+}
+
+/// Fetches checksums from the container, or `None` when it can't compute
+/// them, such as when `sha256sum` isn't installed.
+fn remote_checksums(
+    client: &KubeClient,
+    pod: &k8s::Pod,
+    container: &str,
+    remote: &str,
+    is_dir: bool,
+) -> Option<BTreeMap<String, String>> {
+    let script = if is_dir {
+        REMOTE_DIR_CHECKSUMS
+    } else {
+        REMOTE_FILE_CHECKSUM
+    };
+    match client.exec_output(pod, container, &["sh", "-c", script, remote]) {
+        Ok(output) => Some(transfer::parse_checksums(
+            &String::from_utf8_lossy(&output.stdout),
+            !is_dir,
+        )),
+        Err(err) => {
+            log::warn!("Couldn't verify checksums: {:#}", err);
+            None
+        }
+    }
+}
+
+fn verify_checksums(
+    expected: Option<BTreeMap<String, String>>,
+    actual: Option<BTreeMap<String, String>>,
+) -> anyhow::Result<()> {
+    if let (Some(expected), Some(actual)) = (expected, actual) {
+        transfer::verify(&expected, &actual)?;
+        log::info!("Verified checksums for {} files", expected.len());
+    }
+    Ok(())
+}
+
+fn file_name(path: &str) -> anyhow::Result<&str> {
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+        .ok_or(anyhow::anyhow!("Can't copy {} without a file name", path))
+}
+
+fn run_tar(runner: &dyn CommandRunner, args: &[&str]) -> anyhow::Result<()> {
+    log::debug!("Running tar with {:?}", args);
+    let output = runner.output("tar", args)?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "tar {:?}: {} ({})",
+            args,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
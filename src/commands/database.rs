@@ -0,0 +1,319 @@
+use super::config::{self, with_console_env};
+use super::console::POD_START_TIMEOUT;
+use super::forward::{Forwarder, Target, RECONNECT_DELAY};
+use crate::flightctl::audit;
+use crate::flightctl::database::ConnectionUrl;
+use crate::flightctl::interrupt::Interrupts;
+use crate::flightctl::kubeclient::{self, KubeClient};
+use crate::flightctl::kubectl;
+use crate::flightctl::kubeenv::{self, ResolvedValue, ResolvedVar};
+use crate::flightctl::podspec;
+use crate::flightctl::runner::CommandRunner;
+use crate::flightctl::{Config, Database, Release};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+/// How long a relay pod may run before Kubernetes stops it, in case
+/// flightctl never gets to delete it.
+const RELAY_DEADLINE_SECONDS: i64 = 12 * 60 * 60;
+
+/// How long to wait for a tunnel to accept connections locally.
+const TUNNEL_START_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often to check a tunnel's connection while it's open.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often to check on a database client and its tunnel, which also
+/// limits how long flightctl lingers after the client exits.
+const CLIENT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const LOCALHOST: &str = "127.0.0.1";
+
+#[derive(Debug, StructOpt)]
+pub struct TunnelOptions {
+    /// Database to connect to, as configured in `databases`
+    pub name: Option<String>,
+
+    /// Local port to listen on, instead of the configured or a free port
+    #[structopt(long)]
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct PsqlOptions {
+    /// Database to connect to, as configured in `databases`
+    pub name: Option<String>,
+
+    /// Extra arguments for psql, such as -- --command 'select 1'
+    #[structopt(last = true)]
+    pub args: Vec<String>,
+}
+
+/// Opens a tunnel to a database and keeps it open until interrupted,
+/// printing the local address to connect to.
+pub fn tunnel(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    options: &TunnelOptions,
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
+    let (name, database) = application.find_database(options.name.as_deref())?;
+    let remote = connection_url(runner, config, release, database)?;
+    let local_port = local_port(options.port.or(database.local_port))?;
+
+    let mut interrupts = Interrupts::catch()?;
+    let client = kubeclient::new(runner, &release.context);
+    with_tunnel(
+        &client,
+        name,
+        database,
+        &remote,
+        local_port,
+        |local, forwarder| {
+            println!("{}: {}", name, local);
+            println!("Press Ctrl-C to close the tunnel");
+            while !interrupts.wait(POLL_INTERVAL) {
+                forwarder.poll()?;
+            }
+            Ok(())
+        },
+    )
+}
+
+/// Opens a tunnel to a PostgreSQL database and runs psql through it, passing
+/// credentials in its environment.
+pub fn psql(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    options: &PsqlOptions,
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
+    let (name, database) = application.find_database(options.name.as_deref())?;
+    let remote = connection_url(runner, config, release, database)?;
+    // Check that this is a PostgreSQL database before starting a relay.
+    remote.libpq_env()?;
+    let local_port = local_port(database.local_port)?;
+
+    // psql handles Ctrl-C itself to cancel queries, so it mustn't stop
+    // flightctl before the relay is cleaned up.
+    let _interrupts = Interrupts::catch()?;
+    let client = kubeclient::new(runner, &release.context);
+    with_tunnel(
+        &client,
+        name,
+        database,
+        &remote,
+        local_port,
+        |local, forwarder| {
+            run_client(
+                runner,
+                forwarder,
+                "psql",
+                &options.args,
+                &local.libpq_env()?,
+            )
+        },
+    )
+}
+
+/// Runs a local database client with connection settings in its
+/// environment, keeping the tunnel connected until the client exits.
+pub fn run_client(
+    runner: &dyn CommandRunner,
+    forwarder: &mut Forwarder,
+    program: &str,
+    args: &[String],
+    env: &[(&str, String)],
+) -> anyhow::Result<()> {
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    let env: Vec<(&str, &str)> = env
+        .iter()
+        .map(|(name, value)| (*name, value.as_str()))
+        .collect();
+    log::debug!("Running {} with {:?}", program, &args);
+    let mut client = runner.spawn_with_env(program, &args, &env)?;
+    let status = loop {
+        if let Some(status) = client.try_wait()? {
+            break status;
+        }
+        if let Err(err) = forwarder.poll() {
+            client.kill()?;
+            return Err(err);
+        }
+        thread::sleep(CLIENT_POLL_INTERVAL);
+    };
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "{} exited unsuccessfully ({})",
+            program,
+            status
+        ))
+    }
+}
+
+/// Starts a relay pod which connects to the database, forwards `local_port`
+/// to it, and calls `f` with the local URL once it accepts connections. The
+/// relay is deleted afterwards, even when `f` fails.
+pub fn with_tunnel<T, F>(
+    client: &KubeClient,
+    name: &str,
+    database: &Database,
+    remote: &ConnectionUrl,
+    local_port: u16,
+    f: F,
+) -> anyhow::Result<T>
+where
+    F: FnOnce(&ConnectionUrl, &mut Forwarder) -> anyhow::Result<T>,
+{
+    let port = remote.port()?;
+    let pod = podspec::relay_pod(
+        &format!("{}-db", name),
+        &database.image,
+        &remote.host,
+        port,
+        RELAY_DEADLINE_SECONDS,
+    );
+    let manifest = kubectl::write_temp(&serde_json::to_string(&pod)?)?;
+    let relay = client
+        .create(&manifest)?
+        .into_iter()
+        .next()
+        .ok_or(anyhow::Error::msg("kubectl didn't report the created pod"))?;
+    manifest.close()?;
+
+    log::info!("Waiting for {} to start", relay);
+    let result = client
+        .wait(&relay, "condition=Ready", POD_START_TIMEOUT)
+        .and_then(|_| {
+            let mut forwarder = Forwarder::new(client, RECONNECT_DELAY);
+            forwarder.add(name, Target::Resource(relay.clone()), local_port, port);
+            forwarder.poll()?;
+            wait_for_port(&mut forwarder, local_port)?;
+            f(&remote.with_address(LOCALHOST, local_port), &mut forwarder)
+        });
+
+    log::info!("Deleting {}", relay);
+    let deleted = client.delete(&relay);
+    result.and_then(|value| deleted.map(|_| value))
+}
+
+/// Reads a database's connection URL from the environment of its console's
+/// container, fetching any secrets it uses and recording them in the audit
+/// log.
+fn connection_url(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    database: &Database,
+) -> anyhow::Result<ConnectionUrl> {
+    with_console_env(
+        runner,
+        config,
+        release,
+        database.console.as_deref(),
+        None,
+        |resolver, vars| {
+            let var = find_var(&vars, &database.env).ok_or(anyhow::anyhow!(
+                "{} isn't set for {}",
+                database.env,
+                release
+            ))?;
+
+            let url = match &var.value {
+                ResolvedValue::Pod {
+                    template: Some(template),
+                    ..
+                } => {
+                    let mut secrets = HashMap::new();
+                    for referenced in &vars {
+                        if let ResolvedValue::SecretKeyRef { secret, key } = &referenced.value {
+                            if !referenced.overridden
+                                && template.contains(&format!("$({})", referenced.name))
+                            {
+                                let value = reveal(resolver, release, referenced, secret, key)?;
+                                secrets.insert(referenced.name.to_string(), value);
+                            }
+                        }
+                    }
+                    kubeenv::expand(template, |name| {
+                        secrets.get(name).cloned().or_else(|| {
+                            find_var(&vars, name)
+                                .and_then(|var| var.value.expansion())
+                                .map(String::from)
+                        })
+                    })
+                }
+                ResolvedValue::SecretKeyRef { secret, key } => {
+                    reveal(resolver, release, var, secret, key)?
+                }
+                value => value.expansion().map(String::from).ok_or(anyhow::anyhow!(
+                    "Couldn't read {} from {}",
+                    database.env,
+                    config::source(value)
+                ))?,
+            };
+            url.parse()
+        },
+    )
+}
+
+fn find_var<'v>(vars: &'v [ResolvedVar], name: &str) -> Option<&'v ResolvedVar> {
+    vars.iter()
+        .find(|var| !var.overridden && var.name.as_str() == name)
+}
+
+fn reveal(
+    resolver: &mut kubeenv::Resolver,
+    release: &Release,
+    var: &ResolvedVar,
+    secret: &str,
+    key: &str,
+) -> anyhow::Result<String> {
+    let source = config::source(&var.value);
+    audit::record(
+        &audit::default_path()?,
+        &audit::Entry::new(&release.name, &release.context, &var.name, &source),
+    )?;
+    resolver
+        .secret_value(secret, key)
+        .map(|value| value.to_string())
+        .ok_or(anyhow::anyhow!(
+            "Couldn't read {} from {}",
+            var.name,
+            source
+        ))
+}
+
+/// Uses the requested port, or asks the system for a free one.
+fn local_port(port: Option<u16>) -> anyhow::Result<u16> {
+    match port {
+        Some(port) => Ok(port),
+        None => Ok(TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+            .local_addr()?
+            .port()),
+    }
+}
+
+/// Waits until a forwarded port accepts connections, so clients don't start
+/// before kubectl is listening.
+fn wait_for_port(forwarder: &mut Forwarder, port: u16) -> anyhow::Result<()> {
+    let started = Instant::now();
+    while TcpStream::connect((Ipv4Addr::LOCALHOST, port)).is_err() {
+        if started.elapsed() > TUNNEL_START_TIMEOUT {
+            return Err(anyhow::anyhow!(
+                "Timed out waiting for the tunnel to listen on port {}",
+                port
+            ));
+        }
+        thread::sleep(Duration::from_millis(100));
+        forwarder.poll()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use crate::flightctl::kubeclient;
+use crate::flightctl::kubectl;
+use crate::flightctl::kustomize;
+use crate::flightctl::runner::CommandRunner;
+use crate::flightctl::{Config, ManifestsProvider, Release};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct DeployOptions {
+    /// How long to wait for each rollout, such as 30s or 10m
+    #[structopt(long, default_value = "10m")]
+    pub timeout: String,
+}
+
+pub fn run(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    options: &DeployOptions,
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
+
+    match application.manifests.provider {
+        ManifestsProvider::Kustomize => {
+            let target = kustomize::target(&application.manifests, &release.manifests);
+            let manifests = kustomize::build(runner, &target)?;
+            let resources = kustomize::rollout_resources(&manifests)?;
+
+            let manifests_path = kubectl::write_temp(&manifests)?;
+
+            let client = kubeclient::new(runner, &release.context);
+            log::info!("Applying manifests to {}", release.context);
+            client.apply(&manifests_path)?;
+            manifests_path.close()?;
+
+            let mut failed = Vec::new();
+            for resource in &resources {
+                log::info!("Waiting for {} to roll out", resource);
+                if let Err(err) = client.rollout_status(
+                    &resource.name,
+                    resource.namespace.as_deref(),
+                    &options.timeout,
+                ) {
+                    log::error!("{:#}", err);
+                    failed.push(resource.name.as_str());
+                }
+            }
+
+            if failed.is_empty() {
+                log::info!("Deployed {}", release);
+                Ok(())
+            } else {
+                Err(anyhow::Error::msg(format!(
+                    "Deploying {} failed: {} did not roll out",
+                    release,
+                    failed.join(", ")
+                )))
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::flightctl::kubeclient;
+use crate::flightctl::kubectl;
+use crate::flightctl::kustomize;
+use crate::flightctl::runner::CommandRunner;
+use crate::flightctl::{Config, ManifestsProvider, Release};
+
+/// Shows changes deploying a release would make, returning whether there are
+/// any.
+pub fn run(runner: &dyn CommandRunner, config: &Config, release: &Release) -> anyhow::Result<bool> {
+    let application = config.find_application(release)?;
+
+    match application.manifests.provider {
+        ManifestsProvider::Kustomize => {
+            let target = kustomize::target(&application.manifests, &release.manifests);
+            let manifests = kustomize::build(runner, &target)?;
+            let manifests_path = kubectl::write_temp(&manifests)?;
+
+            let client = kubeclient::new(runner, &release.context);
+            let changed = client.diff(&manifests_path)?;
+            manifests_path.close()?;
+
+            if !changed {
+                log::info!("No changes for {}", release);
+            }
+            Ok(changed)
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::commands::output::{self, OutputFormat};
+use crate::flightctl::events::{self, Record};
+use crate::flightctl::kubeclient::{self, KubeClient};
+use crate::flightctl::runner::CommandRunner;
+use crate::flightctl::workloads;
+use crate::flightctl::{ApplicationConfig, Config, Release};
+use futures::StreamExt;
+use k8s_openapi::api::core::v1 as k8s;
+use kube::api::WatchEvent;
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+use structopt::StructOpt;
+
+/// How long to wait before watching again after the watch fails.
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+const HEADER: [&str; 5] = ["LAST SEEN", "TYPE", "REASON", "OBJECT", "MESSAGE"];
+
+#[derive(Debug, StructOpt)]
+pub struct EventsOptions {
+    /// Keep watching for new events
+    #[structopt(short, long)]
+    pub watch: bool,
+
+    /// Only show warnings, such as failed probes, pulls and scheduling
+    #[structopt(long)]
+    pub warnings_only: bool,
+}
+
+pub fn run(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    format: OutputFormat,
+    options: &EventsOptions,
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
+
+    match &application.config {
+        ApplicationConfig::Kubectl { selector, .. } => {
+            let client = kubeclient::new(runner, &release.context);
+            let selector = kubeclient::Selector::new(selector.clone());
+            if options.watch {
+                if format != OutputFormat::Table {
+                    return Err(anyhow::anyhow!("events --watch only supports table output"));
+                }
+                return watch(&client, &selector, options.warnings_only);
+            }
+
+            let records = fetch(&client, &selector, options.warnings_only)?;
+            output::print(format, records.as_slice(), |records| {
+                if records.is_empty() {
+                    println!("No events found");
+                } else {
+                    output::print_table(&HEADER, records.iter().map(row).collect());
+                }
+            })
+        }
+    }
+}
+
+/// Prints events as they happen, until interrupted. Events about objects
+/// created while watching, such as pods during a rollout, are matched to the
+/// workloads which own them.
+fn watch(
+    client: &KubeClient,
+    selector: &kubeclient::Selector,
+    warnings_only: bool,
+) -> anyhow::Result<()> {
+    // The count last printed for each event, since repeated events are
+    // updated with a higher count. Expired events are deleted, and removed.
+    let mut printed: HashMap<String, i32> = HashMap::new();
+    println!("{}", output::format_table(&HEADER, Vec::new())[0]);
+    loop {
+        let resources = workloads::fetch(client, selector)?;
+        let mut listed = Vec::new();
+        let mut changes = Vec::new();
+        for kind in events::KINDS {
+            let (events, watched) = client.watch_events(kind)?;
+            listed.extend(events);
+            changes.push(watched);
+        }
+        printed.retain(|name, _| {
+            listed
+                .iter()
+                .any(|event| event.metadata.name.as_ref() == Some(name))
+        });
+        show(
+            events::select(listed, &resources, warnings_only),
+            &mut printed,
+        );
+
+        let mut changes = futures::stream::select_all(changes);
+        let closed = client.block_on(async {
+            while let Some(change) = changes.next().await {
+                match change? {
+                    WatchEvent::Added(event) | WatchEvent::Modified(event) => show(
+                        events::select(vec![event], &resources, warnings_only),
+                        &mut printed,
+                    ),
+                    WatchEvent::Deleted(event) => {
+                        if let Some(name) = &event.metadata.name {
+                            printed.remove(name);
+                        }
+                    }
+                    WatchEvent::Bookmark(_) => {}
+                    WatchEvent::Error(err) => return Err(anyhow::Error::from(err)),
+                }
+            }
+            anyhow::Ok(())
+        })?;
+        match closed {
+            Ok(()) => log::debug!("Watch closed; reopening"),
+            Err(err) => {
+                log::warn!("Couldn't watch events: {:#}; retrying", err);
+                thread::sleep(RETRY_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Prints the records which haven't been printed with their current count.
+fn show(records: Vec<Record>, printed: &mut HashMap<String, i32>) {
+    let new: Vec<Vec<String>> = records
+        .iter()
+        .filter(|record| printed.insert(record.name.clone(), record.count) != Some(record.count))
+        .map(row)
+        .collect();
+    if !new.is_empty() {
+        for line in &output::format_table(&HEADER, new)[1..] {
+            println!("{}", line);
+        }
+    }
+}
+
+fn fetch(
+    client: &KubeClient,
+    selector: &kubeclient::Selector,
+    warnings_only: bool,
+) -> anyhow::Result<Vec<Record>> {
+    let resources = workloads::fetch(client, selector)?;
+    let events: Vec<k8s::Event> =
+        client.list_resources(&kubeclient::Selector::new(HashMap::new()))?;
+    Ok(events::select(events, &resources, warnings_only))
+}
+
+pub fn row(record: &Record) -> Vec<String> {
+    let object = if record.count > 1 {
+        format!("{} (x{})", record.object, record.count)
+    } else {
+        record.object.clone()
+    };
+    vec![
+        output::show_age(record.time.as_ref()),
+        record.type_.clone(),
+        record.reason.clone(),
+        object,
+        record.message.clone(),
+    ]
+}
@@ -0,0 +1,189 @@
+use crate::flightctl::interrupt::Interrupts;
+use crate::flightctl::kubeclient::{self, KubeClient};
+use crate::flightctl::runner::{CommandRunner, Process};
+use crate::flightctl::{ApplicationConfig, Config, Forward, Release};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How often to check whether forwards are still connected.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long to wait before reconnecting a forward which disconnected, such as
+/// when its pod is replaced during a rollout.
+pub(crate) const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Where a forward connects to in the cluster.
+#[derive(Debug)]
+pub enum Target {
+    Service(String),
+    Pod(kubeclient::Selector),
+
+    /// A specific resource, such as `pod/web-relay-x7k2q`.
+    Resource(String),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Service(name) => write!(f, "service/{}", name),
+            Target::Pod(selector) => write!(f, "pod matching {}", selector.to_string()),
+            Target::Resource(resource) => write!(f, "{}", resource),
+        }
+    }
+}
+
+/// Keeps a set of port forwards connected, restarting each one when its
+/// connection ends.
+#[derive(Debug)]
+pub struct Forwarder<'c> {
+    client: &'c KubeClient<'c>,
+    forwards: Vec<Connection<'c>>,
+    reconnect_delay: Duration,
+}
+
+#[derive(Debug)]
+struct Connection<'c> {
+    name: &'c str,
+    target: Target,
+    local_port: u16,
+    remote_port: u16,
+    process: Option<Box<dyn Process>>,
+    retry_at: Instant,
+}
+
+/// Forwards local ports for a release, printing the local address of each,
+/// until interrupted.
+pub fn run(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    names: &[String],
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
+    let selector = match &application.config {
+        ApplicationConfig::Kubectl { selector, .. } => selector,
+    };
+    let forwards = application.find_forwards(names)?;
+
+    let client = kubeclient::new(runner, &release.context);
+    let mut forwarder = Forwarder::new(&client, RECONNECT_DELAY);
+    for (name, forward) in &forwards {
+        let target = target(forward, kubeclient::Selector::new(selector.clone()));
+        println!(
+            "{}: {} -> {} port {}",
+            name,
+            forward.local_url(),
+            target,
+            forward.port
+        );
+        forwarder.add(name, target, forward.local_port(), forward.port);
+    }
+
+    // Forwards run in their own process group, so they're stopped when the
+    // forwarder is dropped rather than by Ctrl-C.
+    let mut interrupts = Interrupts::catch()?;
+    while !interrupts.wait(POLL_INTERVAL) {
+        forwarder.poll()?;
+    }
+    Ok(())
+}
+
+/// Finds where a forward connects to: its service, or a pod matching both the
+/// application's and the forward's selectors.
+pub fn target(forward: &Forward, application: kubeclient::Selector) -> Target {
+    match &forward.service {
+        Some(service) => Target::Service(service.clone()),
+        None => {
+            Target::Pod(application.extend(&kubeclient::Selector::new(forward.selector.clone())))
+        }
+    }
+}
+
+impl<'c> Forwarder<'c> {
+    pub fn new(client: &'c KubeClient<'c>, reconnect_delay: Duration) -> Forwarder<'c> {
+        Forwarder {
+            client,
+            forwards: Vec::new(),
+            reconnect_delay,
+        }
+    }
+
+    pub fn add(&mut self, name: &'c str, target: Target, local_port: u16, remote_port: u16) {
+        self.forwards.push(Connection {
+            name,
+            target,
+            local_port,
+            remote_port,
+            process: None,
+            retry_at: Instant::now(),
+        });
+    }
+
+    /// Connects forwards which aren't running and are due to retry, and notes
+    /// any which have disconnected so they reconnect after a delay.
+    pub fn poll(&mut self) -> anyhow::Result<()> {
+        let now = Instant::now();
+        for forward in &mut self.forwards {
+            match &mut forward.process {
+                Some(process) => {
+                    if let Some(status) = process.try_wait()? {
+                        log::warn!(
+                            "Forward {} disconnected ({}); reconnecting",
+                            forward.name,
+                            status
+                        );
+                        forward.process = None;
+                        forward.retry_at = now + self.reconnect_delay;
+                    }
+                }
+                None if now >= forward.retry_at => match resource(self.client, &forward.target) {
+                    Ok(Some(resource)) => {
+                        log::debug!("Forwarding {} to {}", forward.name, resource);
+                        forward.process = Some(self.client.port_forward(
+                            &resource,
+                            forward.local_port,
+                            forward.remote_port,
+                        )?);
+                    }
+                    Ok(None) => {
+                        log::warn!("No running pod for forward {}; retrying", forward.name);
+                        forward.retry_at = now + self.reconnect_delay;
+                    }
+                    Err(err) => {
+                        log::warn!(
+                            "Couldn't find a pod for forward {}: {:#}; retrying",
+                            forward.name,
+                            err
+                        );
+                        forward.retry_at = now + self.reconnect_delay;
+                    }
+                },
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Forwarder<'_> {
+    fn drop(&mut self) {
+        for process in self.forwards.iter_mut().filter_map(|f| f.process.as_mut()) {
+            if let Err(err) = process.kill() {
+                log::warn!("Couldn't stop port forward: {:#}", err);
+            }
+        }
+    }
+}
+
+/// Picks the resource to forward to. Pods are looked up on every connection
+/// so that forwards follow rollouts to new pods.
+fn resource(client: &KubeClient, target: &Target) -> anyhow::Result<Option<String>> {
+    match target {
+        Target::Service(name) => Ok(Some(format!("service/{}", name))),
+        Target::Pod(selector) => Ok(client
+            .find_available_pod(selector)?
+            .and_then(|pod| pod.metadata.name)
+            .map(|name| format!("pod/{}", name))),
+        Target::Resource(resource) => Ok(Some(resource.clone())),
+    }
+}
@@ -0,0 +1,118 @@
+use super::console::POD_START_TIMEOUT;
+use super::logs::parse_duration;
+use crate::flightctl::kubeclient::{self, KubeClient};
+use crate::flightctl::kubectl;
+use crate::flightctl::podspec;
+use crate::flightctl::runner::CommandRunner;
+use crate::flightctl::{ApplicationConfig, Config, Console, Release};
+use k8s_openapi::api::batch::v1 as batch;
+use std::path::PathBuf;
+use std::time::Duration;
+use structopt::clap::ArgGroup;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(group = ArgGroup::with_name("as-job").multiple(true))]
+pub struct RunOptions {
+    /// Run the command as a Job, streaming its logs and exiting with its
+    /// exit code
+    #[structopt(long, group = "as-job")]
+    pub job: bool,
+
+    /// Start the command as a Job and return without waiting for it
+    #[structopt(long, group = "as-job")]
+    pub detach: bool,
+
+    /// Upload a local script and run it with the command, such as
+    /// --script ./fix.rb -- rails runner
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["job", "detach"])]
+    pub script: Option<PathBuf>,
+
+    /// How long the job may run, such as 30m or 2h [default: 1h]. Requires
+    /// --job or --detach
+    #[structopt(long, requires = "as-job", parse(try_from_str = parse_duration))]
+    pub timeout: Option<i64>,
+}
+
+/// How long jobs may run, in seconds, unless --timeout is given.
+const DEFAULT_JOB_TIMEOUT: i64 = 60 * 60;
+
+impl RunOptions {
+    fn job_timeout(&self) -> i64 {
+        self.timeout.unwrap_or(DEFAULT_JOB_TIMEOUT)
+    }
+}
+
+/// Runs a command as a Job cloned from the default console's deployment,
+/// returning the command's exit code.
+pub fn run(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    cmd: &[String],
+    options: &RunOptions,
+) -> anyhow::Result<i32> {
+    let application = config.find_application(release)?;
+    let (container, console_selector) = match application.find_console(None)? {
+        Console::Exec {
+            container,
+            selector,
+            ..
+        }
+        | Console::Run {
+            container,
+            selector,
+            ..
+        }
+        | Console::Debug {
+            container,
+            selector,
+            ..
+        } => (container, selector),
+    };
+
+    match &application.config {
+        ApplicationConfig::Kubectl { selector, .. } => {
+            let client = kubeclient::new(runner, &release.context);
+            let base_selector = kubeclient::Selector::new(selector.clone());
+            let pod_selector =
+                base_selector.extend(&kubeclient::Selector::new(console_selector.clone()));
+            let (deployment, template) = client.get_pod_template(&base_selector, &pod_selector)?;
+            let job = podspec::job(template, &deployment, container, cmd, options.job_timeout())?;
+            run_job(&client, &job, container, options)
+        }
+    }
+}
+
+/// Creates a job and, unless detached, follows its logs until it finishes.
+pub fn run_job(
+    client: &KubeClient,
+    job: &batch::Job,
+    container: &str,
+    options: &RunOptions,
+) -> anyhow::Result<i32> {
+    let manifest = kubectl::write_temp(&serde_json::to_string(job)?)?;
+    let name = client
+        .create(&manifest)?
+        .into_iter()
+        .next()
+        .ok_or(anyhow::Error::msg("kubectl didn't report the created job"))?;
+    manifest.close()?;
+
+    if options.detach {
+        log::info!("Started {}", name);
+        println!("{}", name);
+        return Ok(0);
+    }
+
+    log::info!("Started {}; streaming its logs", name);
+    if let Err(err) = client.follow_logs(&name, container, POD_START_TIMEOUT) {
+        log::warn!("{:#}", err);
+    }
+
+    let job_name = name.rsplit('/').next().unwrap_or(&name);
+    let timeout = Duration::from_secs(options.job_timeout().max(0) as u64);
+    let code = client.wait_for_exit(job_name, container, timeout)?;
+    log::info!("{} exited with status {}", name, code);
+    Ok(code)
+}
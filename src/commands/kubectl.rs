@@ -1,12 +1,18 @@
 use crate::flightctl::kubeclient;
+use crate::flightctl::runner::CommandRunner;
 use crate::flightctl::{ApplicationConfig, Config, Release};
 
-pub fn run(config: &Config, release: &Release, cmd: &Vec<String>) -> anyhow::Result<()> {
+pub fn run(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    cmd: &Vec<String>,
+) -> anyhow::Result<()> {
     let application = config.find_application(&release)?;
 
     match &application.config {
         ApplicationConfig::Kubectl { .. } => {
-            let client = kubeclient::new(&release.context);
+            let client = kubeclient::new(runner, &release.context);
             client.run_command(cmd)
         }
     }
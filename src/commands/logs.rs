@@ -0,0 +1,149 @@
+use crate::flightctl::kubeclient::{self, LogTarget};
+use crate::flightctl::runner::CommandRunner;
+use crate::flightctl::{ApplicationConfig, Config, Release};
+use k8s_openapi::api::core::v1 as k8s;
+use kube::api::LogParams;
+use std::io::{self, Write};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct LogOptions {
+    /// Continue streaming new log lines
+    #[structopt(short, long)]
+    pub follow: bool,
+
+    /// Only show lines newer than a duration, such as 30s, 5m or 1h30m
+    #[structopt(long, parse(try_from_str = parse_duration))]
+    pub since: Option<i64>,
+
+    /// Number of recent lines to show from each container
+    #[structopt(long)]
+    pub tail: Option<i64>,
+
+    /// Only show logs from this container
+    #[structopt(short, long)]
+    pub container: Option<String>,
+
+    /// Show logs from the previous instance of each container
+    #[structopt(short, long)]
+    pub previous: bool,
+}
+
+pub fn run(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    options: &LogOptions,
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
+
+    match &application.config {
+        ApplicationConfig::Kubectl { selector, .. } => {
+            let client = kubeclient::new(runner, &release.context);
+            let pods: Vec<k8s::Pod> =
+                client.list_resources(&kubeclient::Selector::new(selector.clone()))?;
+            let targets = log_targets(&pods, options.container.as_deref());
+
+            if targets.is_empty() {
+                return Err(anyhow::Error::msg(format!(
+                    "No running pods found for application: {}",
+                    application.name
+                )));
+            }
+
+            let mut pod_names: Vec<&str> =
+                targets.iter().map(|target| target.pod.as_str()).collect();
+            pod_names.dedup();
+            let show_container = pod_names.len() < targets.len();
+            let params = LogParams {
+                follow: options.follow,
+                previous: options.previous,
+                since_seconds: options.since,
+                tail_lines: options.tail,
+                ..LogParams::default()
+            };
+            let stdout = io::stdout();
+            let mut output = stdout.lock();
+
+            client.stream_logs(&targets, &params, |target, line| {
+                if show_container {
+                    writeln!(output, "[{}/{}] {}", target.pod, target.container, line)?;
+                } else {
+                    writeln!(output, "[{}] {}", target.pod, line)?;
+                }
+                Ok(())
+            })
+        }
+    }
+}
+
+/// Lists the containers to stream logs from. Pods which aren't running, such
+/// as those still pending, have no logs to stream and are skipped.
+pub fn log_targets(pods: &[k8s::Pod], container: Option<&str>) -> Vec<LogTarget> {
+    let mut targets = Vec::new();
+
+    for pod in pods {
+        let pod_name = match &pod.metadata.name {
+            Some(name) => name,
+            None => continue,
+        };
+        let phase = pod
+            .status
+            .as_ref()
+            .and_then(|status| status.phase.as_deref())
+            .unwrap_or("Unknown");
+        if phase != "Running" {
+            log::debug!("Skipping pod {}, which is {}", pod_name, phase);
+            continue;
+        }
+        let containers = pod
+            .spec
+            .as_ref()
+            .map(|spec| spec.containers.as_slice())
+            .unwrap_or_default();
+
+        for pod_container in containers {
+            if container.is_none_or(|name| name == pod_container.name) {
+                targets.push(LogTarget {
+                    pod: pod_name.clone(),
+                    container: pod_container.name.clone(),
+                });
+            }
+        }
+    }
+
+    targets
+}
+
+pub fn parse_duration(value: &str) -> Result<i64, String> {
+    let mut seconds = 0;
+    let mut digits = String::new();
+
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            _ => return Err(format!("Unknown unit '{}' in duration {}", c, value)),
+        };
+        let amount: i64 = digits
+            .parse()
+            .map_err(|_| format!("Invalid duration: {}", value))?;
+        seconds += amount * unit;
+        digits.clear();
+    }
+
+    if digits.is_empty() && !value.is_empty() {
+        Ok(seconds)
+    } else {
+        Err(format!(
+            "Invalid duration: {} (expected a value like 30s, 5m or 1h30m)",
+            value
+        ))
+    }
+}
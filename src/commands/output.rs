@@ -0,0 +1,98 @@
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use k8s_openapi::chrono::Utc;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Table,
+    Yaml,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<OutputFormat, String> {
+        match value {
+            "json" => Ok(OutputFormat::Json),
+            "table" => Ok(OutputFormat::Table),
+            "yaml" => Ok(OutputFormat::Yaml),
+            _ => Err(format!(
+                "Unknown output format {} (expected json, table or yaml)",
+                value
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Table => write!(f, "table"),
+            OutputFormat::Yaml => write!(f, "yaml"),
+        }
+    }
+}
+
+/// Prints a value as JSON or YAML, or calls `table` to print it for humans.
+pub fn print<T, F>(format: OutputFormat, value: &T, table: F) -> anyhow::Result<()>
+where
+    T: Serialize + ?Sized,
+    F: FnOnce(&T),
+{
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        OutputFormat::Table => table(value),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+    }
+    Ok(())
+}
+
+/// Prints rows aligned into columns beneath a header.
+pub fn print_table(header: &[&str], rows: Vec<Vec<String>>) {
+    for line in format_table(header, rows) {
+        println!("{}", line);
+    }
+}
+
+/// Aligns rows into columns beneath a header, returning each line.
+pub fn format_table(header: &[&str], rows: Vec<Vec<String>>) -> Vec<String> {
+    let header: Vec<String> = header.iter().map(|title| title.to_string()).collect();
+    let mut widths: Vec<usize> = header.iter().map(|title| title.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect();
+            cells.join("   ").trim_end().to_string()
+        })
+        .collect()
+}
+
+/// Formats the time since a timestamp the way kubectl does, such as `5m`.
+pub fn show_age(timestamp: Option<&Time>) -> String {
+    match timestamp {
+        Some(Time(time)) => {
+            let seconds = Utc::now().signed_duration_since(*time).num_seconds().max(0);
+            match seconds {
+                0..=119 => format!("{}s", seconds),
+                120..=7199 => format!("{}m", seconds / 60),
+                7200..=172799 => format!("{}h", seconds / 3600),
+                _ => format!("{}d", seconds / 86400),
+            }
+        }
+        None => String::from("<unknown>"),
+    }
+}
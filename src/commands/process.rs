@@ -1,13 +1,300 @@
+use crate::commands::output::{self, OutputFormat};
+use crate::flightctl::diagnosis::{self, Finding};
 use crate::flightctl::kubeclient;
+use crate::flightctl::runner::CommandRunner;
+use crate::flightctl::workloads::{self, Process, Workload};
 use crate::flightctl::{ApplicationConfig, Config, Release};
+use k8s_openapi::api::core::v1 as k8s;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, IsTerminal};
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
 
-pub fn run(config: &Config, release: &Release) -> anyhow::Result<()> {
-    let application = config.find_application(&release)?;
+/// How often the watch view is redrawn when nothing changes, to keep ages
+/// current.
+const WATCH_REFRESH: Duration = Duration::from_secs(5);
+
+/// How long pods stay highlighted after they change.
+const HIGHLIGHT_DURATION: Duration = Duration::from_secs(15);
+
+/// How many log lines to show from a container which crashed.
+const CRASH_LOG_LINES: i64 = 10;
+
+#[derive(Debug, StructOpt)]
+pub struct PsOptions {
+    /// Keep watching for changes, highlighting pods which change state
+    #[structopt(short, long)]
+    pub watch: bool,
+
+    /// Explain what's wrong with unhealthy pods, with the evidence
+    #[structopt(long, conflicts_with = "watch")]
+    pub explain: bool,
+}
+
+pub fn run(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+    format: OutputFormat,
+    options: &PsOptions,
+) -> anyhow::Result<()> {
+    let application = config.find_application(release)?;
 
     match &application.config {
         ApplicationConfig::Kubectl { selector, .. } => {
-            let client = kubeclient::new(&release.context);
-            client.get_workloads(kubeclient::Selector::new(selector.clone()))
+            let client = kubeclient::new(runner, &release.context);
+            let selector = kubeclient::Selector::new(selector.clone());
+            if options.watch {
+                if format != OutputFormat::Table {
+                    return Err(anyhow::anyhow!("ps --watch only supports table output"));
+                }
+                return watch(&client, &selector);
+            }
+            if options.explain {
+                return explain(&client, &selector, format);
+            }
+
+            let workloads = workloads::group(workloads::fetch(&client, &selector)?);
+            output::print(format, workloads.as_slice(), |workloads| {
+                for line in render(workloads, &HashSet::new()) {
+                    println!("{}", line);
+                }
+            })
+        }
+    }
+}
+
+/// Redraws the workloads whenever they change, until interrupted.
+fn watch(client: &kubeclient::KubeClient, selector: &kubeclient::Selector) -> anyhow::Result<()> {
+    let color = io::stdout().is_terminal();
+    let mut tracker = ChangeTracker::default();
+
+    workloads::watch(client, selector, WATCH_REFRESH, |resources, resynced| {
+        let workloads = workloads::group(resources.clone());
+        let highlighted = if resynced {
+            tracker.resync(&workloads, Instant::now())
+        } else {
+            tracker.update(&workloads, Instant::now())
+        };
+
+        // Clear the screen and move to the top left before redrawing.
+        print!("\x1b[2J\x1b[H");
+        for line in render(&workloads, &highlighted) {
+            if color && line.starts_with('*') {
+                println!("\x1b[1;33m{}\x1b[0m", line);
+            } else {
+                println!("{}", line);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Diagnoses each pod using recent events and the logs of crashed
+/// containers.
+fn explain(
+    client: &kubeclient::KubeClient,
+    selector: &kubeclient::Selector,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    let pods: Vec<k8s::Pod> = client.list_resources(selector)?;
+    // Events don't have the labels of the objects they're about, so they're
+    // matched to pods by name.
+    let events = client.list_events("Pod")?;
+
+    let mut findings = Vec::new();
+    for pod in &pods {
+        let name = pod.metadata.name.as_deref().unwrap_or_default();
+        let mut logs = HashMap::new();
+        for (container, previous) in diagnosis::crashed_containers(pod) {
+            match client.last_logs(name, &container, previous, CRASH_LOG_LINES) {
+                Ok(lines) => {
+                    logs.insert(container, lines);
+                }
+                Err(err) => log::debug!("Couldn't read logs for {}: {:#}", container, err),
+            }
+        }
+        findings.extend(diagnosis::diagnose(pod, &events, &logs));
+    }
+
+    output::print(format, findings.as_slice(), |findings| {
+        for line in render_findings(pods.len(), findings) {
+            println!("{}", line);
+        }
+    })
+}
+
+/// Formats each finding under its pod, with its evidence indented beneath.
+pub fn render_findings(pods: usize, findings: &[Finding]) -> Vec<String> {
+    if findings.is_empty() {
+        return vec![match pods {
+            0 => String::from("No pods found"),
+            1 => String::from("The pod looks healthy"),
+            pods => format!("All {} pods look healthy", pods),
+        }];
+    }
+
+    let mut lines = Vec::new();
+    let mut pod = None;
+    for finding in findings {
+        if pod != Some(&finding.pod) {
+            if pod.is_some() {
+                lines.push(String::new());
+            }
+            lines.push(format!("pod/{}", finding.pod));
+            pod = Some(&finding.pod);
+        }
+        lines.push(format!("  {}: {}", finding.problem, finding.explanation));
+        for evidence in &finding.evidence {
+            lines.push(format!("    {}", evidence));
         }
     }
+    lines
+}
+
+/// Remembers the state of each pod between updates to find the pods which
+/// recently changed state, appeared, or restarted.
+#[derive(Debug, Default)]
+pub struct ChangeTracker {
+    states: Option<HashMap<String, (String, usize, i32)>>,
+    changed: HashMap<String, Instant>,
+}
+
+impl ChangeTracker {
+    /// Records the current state of each pod, returning the pods which
+    /// changed within the last [`HIGHLIGHT_DURATION`]. Nothing is
+    /// highlighted the first time, since there's nothing to compare.
+    pub fn update(&mut self, workloads: &[Workload], now: Instant) -> HashSet<String> {
+        self.record(workloads, now, true)
+    }
+
+    /// Records the state of each pod after the workloads were listed afresh,
+    /// such as when a watch is reopened. Pods which were already known are
+    /// highlighted if they changed, but pods seen for the first time aren't,
+    /// since there's no telling whether they're new.
+    pub fn resync(&mut self, workloads: &[Workload], now: Instant) -> HashSet<String> {
+        self.record(workloads, now, false)
+    }
+
+    fn record(&mut self, workloads: &[Workload], now: Instant, new: bool) -> HashSet<String> {
+        let states: HashMap<String, (String, usize, i32)> = workloads
+            .iter()
+            .flat_map(|workload| &workload.pods)
+            .map(|pod| {
+                (
+                    pod.name.clone(),
+                    (pod.status.clone(), pod.ready, pod.restarts),
+                )
+            })
+            .collect();
+
+        if let Some(previous) = &self.states {
+            for (name, state) in &states {
+                let changed = match previous.get(name) {
+                    Some(previous) => previous != state,
+                    None => new,
+                };
+                if changed {
+                    self.changed.insert(name.clone(), now);
+                }
+            }
+        }
+        self.states = Some(states);
+        self.changed
+            .retain(|_, changed| now.duration_since(*changed) < HIGHLIGHT_DURATION);
+        self.changed.keys().cloned().collect()
+    }
+}
+
+/// Formats each workload on a line of its own, followed by a table of its
+/// pods, with a blank line between workloads. Rows for `highlighted` pods are
+/// marked with a `*`.
+pub fn render(workloads: &[Workload], highlighted: &HashSet<String>) -> Vec<String> {
+    let mut lines = Vec::new();
+    for workload in workloads {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(describe(workload));
+        if workload.pods.is_empty() {
+            lines.push(String::from("  No pods"));
+            continue;
+        }
+        let table = output::format_table(
+            &[
+                "POD",
+                "STATUS",
+                "READY",
+                "RESTARTS",
+                "AGE",
+                "NODE",
+                "IMAGES",
+                "LAST TERMINATION",
+            ],
+            workload.pods.iter().map(pod_row).collect(),
+        );
+        lines.push(format!("  {}", table[0]));
+        for (pod, line) in workload.pods.iter().zip(&table[1..]) {
+            let marker = if highlighted.contains(&pod.name) {
+                '*'
+            } else {
+                ' '
+            };
+            lines.push(format!("{} {}", marker, line));
+        }
+    }
+    lines
+}
+
+fn describe(workload: &Workload) -> String {
+    let mut details = vec![match workload.desired {
+        Some(desired) => format!("{}/{} ready", workload.ready, desired),
+        None => format!("{} active", workload.ready),
+    }];
+    if let Some(up_to_date) = workload.up_to_date {
+        details.push(format!("{} up-to-date", up_to_date));
+    }
+    if let Some(available) = workload.available {
+        details.push(format!("{} available", available));
+    }
+    if let Some(schedule) = &workload.schedule {
+        details.push(format!("schedule {}", schedule));
+    }
+    details.push(format!(
+        "age {}",
+        output::show_age(workload.created.as_ref())
+    ));
+    format!(
+        "{}/{}   {}",
+        workload.kind.to_lowercase(),
+        workload.name,
+        details.join(", ")
+    )
+}
+
+fn pod_row(pod: &Process) -> Vec<String> {
+    let images: Vec<String> = pod
+        .images
+        .iter()
+        .map(|image| workloads::image_tag(image))
+        .collect();
+    let last_termination = match &pod.last_termination {
+        Some(termination) => format!(
+            "{} (exit {}) {} ago",
+            termination.reason,
+            termination.exit_code,
+            output::show_age(termination.finished.as_ref())
+        ),
+        None => String::from("-"),
+    };
+    vec![
+        pod.name.clone(),
+        pod.status.clone(),
+        format!("{}/{}", pod.ready, pod.containers),
+        pod.restarts.to_string(),
+        output::show_age(pod.created.as_ref()),
+        pod.node.clone().unwrap_or_else(|| String::from("-")),
+        images.join(","),
+        last_termination,
+    ]
 }
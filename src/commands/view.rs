@@ -1,41 +1,80 @@
-use crate::flightctl::Config;
+use crate::commands::output::{self, OutputFormat};
+use crate::flightctl::{Config, Console};
+use serde::Serialize;
+use std::fmt::Display;
 
-pub fn applications(config: Config) -> anyhow::Result<()> {
-    for application in config.applications {
-        println!("{}", application);
-    }
+#[derive(Serialize)]
+struct ConsoleEntry<'a> {
+    application: &'a str,
+    name: &'a str,
 
-    Ok(())
+    #[serde(flatten)]
+    console: &'a Console,
 }
 
-pub fn auth(config: Config) -> anyhow::Result<()> {
-    for auth in config.auth {
-        println!("{}", auth);
-    }
+pub fn applications(config: Config, format: OutputFormat) -> anyhow::Result<()> {
+    output::print(format, config.applications.as_slice(), print_names)
+}
 
-    Ok(())
+pub fn auth(config: Config, format: OutputFormat) -> anyhow::Result<()> {
+    output::print(format, config.auth.as_slice(), print_names)
 }
 
-pub fn clusters(config: Config) -> anyhow::Result<()> {
-    for cluster in config.clusters {
-        println!("{}", cluster);
-    }
+pub fn clusters(config: Config, format: OutputFormat) -> anyhow::Result<()> {
+    output::print(format, config.clusters.as_slice(), print_names)
+}
 
-    Ok(())
+pub fn consoles(config: Config, format: OutputFormat) -> anyhow::Result<()> {
+    let consoles: Vec<ConsoleEntry> = config
+        .applications
+        .iter()
+        .flat_map(|application| {
+            application
+                .consoles()
+                .into_iter()
+                .map(move |(name, console)| ConsoleEntry {
+                    application: &application.name,
+                    name,
+                    console,
+                })
+        })
+        .collect();
+    output::print(format, consoles.as_slice(), print_consoles)
 }
 
-pub fn contexts(config: Config) -> anyhow::Result<()> {
-    for context in config.contexts {
-        println!("{}", context);
-    }
+pub fn contexts(config: Config, format: OutputFormat) -> anyhow::Result<()> {
+    output::print(format, config.contexts.as_slice(), print_names)
+}
 
-    Ok(())
+pub fn releases(config: Config, format: OutputFormat) -> anyhow::Result<()> {
+    output::print(format, config.releases.as_slice(), print_names)
 }
 
-pub fn releases(config: Config) -> anyhow::Result<()> {
-    for release in config.releases {
-        println!("{}", release);
-    }
+fn print_consoles(consoles: &[ConsoleEntry]) {
+    let rows = consoles
+        .iter()
+        .map(|entry| match entry.console {
+            Console::Exec {
+                command, container, ..
+            }
+            | Console::Run {
+                command, container, ..
+            }
+            | Console::Debug {
+                command, container, ..
+            } => vec![
+                entry.application.to_string(),
+                entry.name.to_string(),
+                container.clone(),
+                command.join(" "),
+            ],
+        })
+        .collect();
+    output::print_table(&["APPLICATION", "NAME", "CONTAINER", "COMMAND"], rows);
+}
 
-    Ok(())
+fn print_names<T: Display>(items: &[T]) {
+    for item in items {
+        println!("{}", item);
+    }
 }
@@ -1,13 +1,24 @@
 mod config;
 mod selector;
 
+pub mod audit;
 pub mod authorize;
 pub mod aws;
 pub mod context;
+pub mod database;
+pub mod diagnosis;
+pub mod events;
+pub mod interrupt;
 pub mod kubeclient;
 pub mod kubeconfig_writer;
 pub mod kubectl;
 pub mod kubeenv;
+pub mod kustomize;
+pub mod podspec;
+pub mod preflight;
+pub mod runner;
+pub mod transfer;
+pub mod workloads;
 
 pub use config::*;
 pub use selector::*;
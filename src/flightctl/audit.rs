@@ -0,0 +1,59 @@
+use k8s_openapi::chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A record of someone viewing a sensitive value, such as a secret.
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub time: String,
+    pub user: String,
+    pub release: String,
+    pub context: String,
+    pub variable: String,
+    pub source: String,
+}
+
+impl Entry {
+    pub fn new(release: &str, context: &str, variable: &str, source: &str) -> Entry {
+        Entry {
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            user: std::env::var("USER").unwrap_or_else(|_| String::from("unknown")),
+            release: String::from(release),
+            context: String::from(context),
+            variable: String::from(variable),
+            source: String::from(source),
+        }
+    }
+}
+
+/// Returns where audit entries are recorded: `FLIGHTCTL_AUDIT_LOG` if set,
+/// otherwise `flightctl/audit.log` in the XDG state directory.
+pub fn default_path() -> anyhow::Result<PathBuf> {
+    if let Some(path) = std::env::var_os("FLIGHTCTL_AUDIT_LOG") {
+        return Ok(PathBuf::from(path));
+    }
+
+    let state_dir = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = std::env::var_os("HOME")
+                .ok_or(anyhow::Error::msg("Can't find the audit log without HOME"))?;
+            Path::new(&home).join(".local").join("state")
+        }
+    };
+    Ok(state_dir.join("flightctl").join("audit.log"))
+}
+
+/// Appends an entry to the audit log as a line of JSON, creating the log
+/// if needed.
+pub fn record(path: &Path, entry: &Entry) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    log::debug!("Recorded access to {} in {}", entry.source, path.display());
+    Ok(())
+}
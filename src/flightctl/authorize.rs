@@ -1,8 +1,9 @@
 use super::aws;
 use super::config::{Auth, AuthConfig, Config, Release};
+use super::runner::CommandRunner;
 use log;
 
-pub fn run(config: &Config, release: &Release) -> anyhow::Result<()> {
+pub fn run(runner: &dyn CommandRunner, config: &Config, release: &Release) -> anyhow::Result<()> {
     log::debug!("Beginning authorization");
     if log::log_enabled!(log::Level::Debug) {
         log::debug!("Checking configured auth for release: {:?}", release);
@@ -15,29 +16,31 @@ pub fn run(config: &Config, release: &Release) -> anyhow::Result<()> {
     if log::log_enabled!(log::Level::Debug) {
         log::debug!("Found auth: {:?}", auth);
     }
-    ensure_auth(auth)?;
+    ensure_auth(runner, auth)?;
     log::debug!("Authorization successful");
     Ok(())
 }
 
-fn ensure_auth(auth: &Auth) -> anyhow::Result<()> {
+fn ensure_auth(runner: &dyn CommandRunner, auth: &Auth) -> anyhow::Result<()> {
     match &auth.config {
         AuthConfig::AwsSso { config: sso_config } => {
             log::info!("Authorizing using AWS SSO");
-            ensure_aws_profile(&auth.name, || aws::create_profile(&auth.name, sso_config))?;
-            aws::verify_auth(&auth.name).or_else(|_| {
-                aws::sso_login(&auth.name)?;
-                aws::verify_auth(&auth.name)
+            ensure_aws_profile(runner, &auth.name, || {
+                aws::create_profile(runner, &auth.name, sso_config)
+            })?;
+            aws::verify_auth(runner, &auth.name).or_else(|_| {
+                aws::sso_login(runner, &auth.name)?;
+                aws::verify_auth(runner, &auth.name)
             })
         }
     }
 }
 
-fn ensure_aws_profile<F>(name: &String, or: F) -> anyhow::Result<()>
+fn ensure_aws_profile<F>(runner: &dyn CommandRunner, name: &str, or: F) -> anyhow::Result<()>
 where
     F: FnOnce() -> anyhow::Result<()>,
 {
-    let profile_exists = aws::profile_exists(name)?;
+    let profile_exists = aws::profile_exists(runner, name)?;
 
     if profile_exists {
         Ok(())
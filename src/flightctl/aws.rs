@@ -1,7 +1,7 @@
+use super::runner::{CommandRunner, ExitStatus, Output};
 use log;
 use serde::Deserialize;
 use std::collections::HashMap;
-use std::process::{Command, ExitStatus, Output};
 
 #[derive(Debug, Deserialize)]
 pub struct EksCluster {
@@ -9,8 +9,8 @@ pub struct EksCluster {
     pub cert: String,
 }
 
-pub fn profile_exists(profile: &str) -> anyhow::Result<bool> {
-    let result = run_aws_cli(&["configure", "list-profiles"])?;
+pub fn profile_exists(runner: &dyn CommandRunner, profile: &str) -> anyhow::Result<bool> {
+    let result = run_aws_cli(runner, &["configure", "list-profiles"])?;
     let output = String::from_utf8(result.stdout)?;
     Ok(output
         .lines()
@@ -18,53 +18,70 @@ pub fn profile_exists(profile: &str) -> anyhow::Result<bool> {
         .is_some())
 }
 
-pub fn create_profile(profile: &str, config: &HashMap<String, String>) -> anyhow::Result<()> {
+pub fn create_profile(
+    runner: &dyn CommandRunner,
+    profile: &str,
+    config: &HashMap<String, String>,
+) -> anyhow::Result<()> {
     log::info!("Creating AWS profile: {}", profile);
 
     for (key, value) in config.iter() {
-        run_aws_cli(&["--profile", profile, "configure", "set", key, value])?;
+        run_aws_cli(
+            runner,
+            &["--profile", profile, "configure", "set", key, value],
+        )?;
     }
 
     Ok(())
 }
 
-pub fn verify_auth(profile: &str) -> anyhow::Result<()> {
-    run_aws_cli(&["--profile", profile, "sts", "get-caller-identity"]).and(Ok(()))
+pub fn verify_auth(runner: &dyn CommandRunner, profile: &str) -> anyhow::Result<()> {
+    run_aws_cli(
+        runner,
+        &["--profile", profile, "sts", "get-caller-identity"],
+    )
+    .and(Ok(()))
 }
 
-pub fn sso_login(profile: &str) -> anyhow::Result<()> {
+pub fn sso_login(runner: &dyn CommandRunner, profile: &str) -> anyhow::Result<()> {
     log::info!("Logging in for AWS profile {}", profile);
-    let args = ["--profile", profile, "sso", "login"];
-    let mut child = aws_cli(&args).spawn()?;
-    let status = child.wait()?;
-    verify_exit(&args, status)
+    run_cli_print(runner, &["--profile", profile, "sso", "login"])
 }
 
-pub fn run_cli_print(args: &[&str]) -> anyhow::Result<()> {
-    let mut child = aws_cli(&args).spawn()?;
-    let status = child.wait()?;
+pub fn run_cli_print(runner: &dyn CommandRunner, args: &[&str]) -> anyhow::Result<()> {
+    log::debug!("Running AWS CLI with {:?}", args);
+    let status = runner.status("aws", args)?;
     verify_exit(&args, status)
 }
 
-pub fn get_eks_cluster(profile: &str, region: &str, name: &str) -> anyhow::Result<EksCluster> {
-    let output = run_aws_cli(&[
-        "--profile",
-        profile,
-        "--region",
-        region,
-        "eks",
-        "describe-cluster",
-        "--name",
-        name,
-        "--query",
-        "cluster.{endpoint:endpoint,cert:certificateAuthority.data}",
-    ])?;
+pub fn get_eks_cluster(
+    runner: &dyn CommandRunner,
+    profile: &str,
+    region: &str,
+    name: &str,
+) -> anyhow::Result<EksCluster> {
+    let output = run_aws_cli(
+        runner,
+        &[
+            "--profile",
+            profile,
+            "--region",
+            region,
+            "eks",
+            "describe-cluster",
+            "--name",
+            name,
+            "--query",
+            "cluster.{endpoint:endpoint,cert:certificateAuthority.data}",
+        ],
+    )?;
     let cluster = serde_yaml::from_slice(&output.stdout)?;
     Ok(cluster)
 }
 
-fn run_aws_cli(args: &[&str]) -> anyhow::Result<Output> {
-    let output = aws_cli(args).output()?;
+fn run_aws_cli(runner: &dyn CommandRunner, args: &[&str]) -> anyhow::Result<Output> {
+    log::debug!("Running AWS CLI with {:?}", args);
+    let output = runner.output("aws", args)?;
     match verify_exit(&args, output.status) {
         Ok(_) => Ok(output),
         Err(err) => {
@@ -73,13 +90,6 @@ fn run_aws_cli(args: &[&str]) -> anyhow::Result<Output> {
     }
 }
 
-fn aws_cli(args: &[&str]) -> Command {
-    log::debug!("Running AWS CLI with {:?}", args);
-    let mut command = Command::new("aws");
-    command.args(args);
-    command
-}
-
 fn verify_exit(args: &[&str], status: ExitStatus) -> anyhow::Result<()> {
     if status.success() {
         Ok(())
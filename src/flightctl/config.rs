@@ -1,9 +1,10 @@
-use serde::Deserialize;
-use std::collections::HashMap;
+use k8s_openapi::api::core::v1::ResourceRequirements;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Auth {
     pub name: String,
 
@@ -17,7 +18,7 @@ impl fmt::Display for Auth {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(tag = "provider", content = "params", rename_all = "kebab-case")]
 pub enum AuthConfig {
     AwsSso {
@@ -26,7 +27,7 @@ pub enum AuthConfig {
     },
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Cluster {
     pub name: String,
 
@@ -42,13 +43,13 @@ impl fmt::Display for Cluster {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(tag = "provider", content = "params", rename_all = "kebab-case")]
 pub enum ClusterConfig {
     Eks { name: String, region: String },
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Application {
     pub manifests: ApplicationManifests,
 
@@ -64,19 +65,149 @@ impl fmt::Display for Application {
     }
 }
 
-#[derive(Debug, Deserialize)]
+impl Application {
+    /// Lists the application's consoles by name. The console configured as
+    /// `console` is named `default`.
+    pub fn consoles(&self) -> Vec<(&str, &Console)> {
+        match &self.config {
+            ApplicationConfig::Kubectl {
+                console, consoles, ..
+            } => console
+                .iter()
+                .map(|console| (DEFAULT_CONSOLE, console))
+                .chain(
+                    consoles
+                        .iter()
+                        .map(|(name, console)| (name.as_str(), console)),
+                )
+                .collect(),
+        }
+    }
+
+    /// Finds a console by name. Without a name, this is the `default`
+    /// console, or the only console if the application has just one.
+    pub fn find_console(&self, name: Option<&str>) -> anyhow::Result<&Console> {
+        let consoles = self.consoles();
+        let names: Vec<&str> = consoles.iter().map(|(name, _)| *name).collect();
+        let found = match name {
+            Some(name) => consoles.iter().find(|(console, _)| *console == name),
+            None if consoles.len() == 1 => consoles.first(),
+            None => consoles
+                .iter()
+                .find(|(console, _)| *console == DEFAULT_CONSOLE),
+        };
+
+        match (found, name) {
+            (Some((_, console)), _) => Ok(console),
+            (None, _) if consoles.is_empty() => Err(anyhow::Error::msg(format!(
+                "No console configured for application: {}",
+                self.name
+            ))),
+            (None, Some(name)) => Err(anyhow::Error::msg(format!(
+                "Application {} has no console named {} (expected one of: {})",
+                self.name,
+                name,
+                names.join(", ")
+            ))),
+            (None, None) => Err(anyhow::Error::msg(format!(
+                "Application {} has several consoles; choose one of: {}",
+                self.name,
+                names.join(", ")
+            ))),
+        }
+    }
+
+    /// Finds a database by name. Without a name, this is the application's
+    /// only database.
+    pub fn find_database(&self, name: Option<&str>) -> anyhow::Result<(&str, &Database)> {
+        let databases = match &self.config {
+            ApplicationConfig::Kubectl { databases, .. } => databases,
+        };
+        let names: Vec<&str> = databases.keys().map(|name| name.as_str()).collect();
+        let found = match name {
+            Some(name) => databases.get_key_value(name),
+            None if databases.len() == 1 => databases.iter().next(),
+            None => None,
+        };
+
+        match (found, name) {
+            (Some((name, database)), _) => Ok((name, database)),
+            (None, _) if databases.is_empty() => Err(anyhow::Error::msg(format!(
+                "No databases configured for application: {}",
+                self.name
+            ))),
+            (None, Some(name)) => Err(anyhow::Error::msg(format!(
+                "Application {} has no database named {} (expected one of: {})",
+                self.name,
+                name,
+                names.join(", ")
+            ))),
+            (None, None) => Err(anyhow::Error::msg(format!(
+                "Application {} has several databases; choose one of: {}",
+                self.name,
+                names.join(", ")
+            ))),
+        }
+    }
+
+    /// Finds port forwards by name, or lists every forward when no names are
+    /// given.
+    pub fn find_forwards(&self, names: &[String]) -> anyhow::Result<Vec<(&str, &Forward)>> {
+        let forwards = match &self.config {
+            ApplicationConfig::Kubectl { forwards, .. } => forwards,
+        };
+        if forwards.is_empty() {
+            return Err(anyhow::Error::msg(format!(
+                "No forwards configured for application: {}",
+                self.name
+            )));
+        }
+        if names.is_empty() {
+            return Ok(forwards
+                .iter()
+                .map(|(name, forward)| (name.as_str(), forward))
+                .collect());
+        }
+
+        names
+            .iter()
+            .map(|name| match forwards.get_key_value(name) {
+                Some((name, forward)) => Ok((name.as_str(), forward)),
+                None => Err(anyhow::Error::msg(format!(
+                    "Application {} has no forward named {} (expected one of: {})",
+                    self.name,
+                    name,
+                    forwards.keys().cloned().collect::<Vec<_>>().join(", ")
+                ))),
+            })
+            .collect()
+    }
+}
+
+pub const DEFAULT_CONSOLE: &str = "default";
+
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(tag = "provider", content = "params", rename_all = "kebab-case")]
 pub enum ApplicationConfig {
     Kubectl {
         #[serde(default)]
         console: Option<Console>,
 
+        #[serde(default)]
+        consoles: BTreeMap<String, Console>,
+
+        #[serde(default)]
+        databases: BTreeMap<String, Database>,
+
+        #[serde(default)]
+        forwards: BTreeMap<String, Forward>,
+
         #[serde(default)]
         selector: HashMap<String, String>,
     },
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(tag = "provider", content = "params", rename_all = "kebab-case")]
 pub enum Console {
     Exec {
@@ -84,21 +215,116 @@ pub enum Console {
         container: String,
         selector: HashMap<String, String>,
     },
+
+    /// Runs the command in a new pod cloned from the template of the
+    /// deployment whose pods match the selector, deleting it afterwards.
+    Run {
+        command: Vec<String>,
+        container: String,
+        selector: HashMap<String, String>,
+
+        #[serde(default)]
+        resources: Option<ResourceRequirements>,
+    },
+
+    /// Attaches an ephemeral debug container to a pod matching the selector,
+    /// sharing the process namespace of `container`, for images without a
+    /// shell.
+    Debug {
+        #[serde(default)]
+        command: Vec<String>,
+        container: String,
+
+        #[serde(default = "default_debug_image")]
+        image: String,
+        selector: HashMap<String, String>,
+    },
+}
+
+/// A database which is only reachable from inside the cluster, such as RDS.
+/// Connections are relayed through a short-lived pod, using a URL read from
+/// a console container's environment.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Database {
+    /// Console whose container environment has the connection URL, which
+    /// defaults to the application's default console.
+    #[serde(default)]
+    pub console: Option<String>,
+
+    /// Variable holding the connection URL.
+    #[serde(default = "default_database_url")]
+    pub env: String,
+
+    /// Image for the relay pod, which must provide `socat`.
+    #[serde(default = "default_relay_image")]
+    pub image: String,
+
+    /// Local port to listen on, which defaults to any free port.
+    #[serde(default)]
+    pub local_port: Option<u16>,
+}
+
+fn default_database_url() -> String {
+    String::from("DATABASE_URL")
+}
+
+fn default_relay_image() -> String {
+    String::from("alpine/socat")
 }
 
-#[derive(Debug, Deserialize)]
+/// Forwards a local port to a port on a service or, when no service is
+/// given, to a pod matching the selector.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Forward {
+    #[serde(default)]
+    pub service: Option<String>,
+
+    #[serde(default)]
+    pub selector: HashMap<String, String>,
+
+    pub port: u16,
+
+    /// Local port to listen on, which defaults to the remote port.
+    #[serde(default)]
+    pub local_port: Option<u16>,
+
+    /// URL scheme used when printing the local address, such as `http`.
+    #[serde(default)]
+    pub scheme: Option<String>,
+}
+
+impl Forward {
+    pub fn local_port(&self) -> u16 {
+        self.local_port.unwrap_or(self.port)
+    }
+
+    /// The address to reach the forward on locally, such as
+    /// `http://localhost:8080`.
+    pub fn local_url(&self) -> String {
+        match &self.scheme {
+            Some(scheme) => format!("{}://localhost:{}", scheme, self.local_port()),
+            None => format!("localhost:{}", self.local_port()),
+        }
+    }
+}
+
+fn default_debug_image() -> String {
+    String::from("busybox")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ApplicationManifests {
-    provider: ManifestsProvider,
-    repo: String,
+    pub provider: ManifestsProvider,
+    pub repo: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum ManifestsProvider {
     Kustomize,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Release {
     pub application: String,
 
@@ -111,7 +337,7 @@ pub struct Release {
     pub name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case", untagged)]
 pub enum ManifestConfig {
     Kustomize { path: String },
@@ -123,7 +349,7 @@ impl fmt::Display for Release {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Context {
     pub auth: String,
     pub cluster: String,
@@ -137,7 +363,7 @@ impl fmt::Display for Context {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct Config {
     pub api_version: String,
@@ -189,6 +415,18 @@ impl Config {
                 release.name, release.context
             )))
     }
+
+    /// Resolves local manifests repositories against the directory containing
+    /// the configuration file, so that commands work from any directory
+    /// beneath it. Remote repositories are left as they are.
+    fn resolve_manifests(&mut self, dir: &Path) {
+        for application in &mut self.applications {
+            let repo = dir.join(&application.manifests.repo);
+            if repo.is_dir() {
+                application.manifests.repo = repo.to_string_lossy().into_owned();
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -199,12 +437,19 @@ pub struct ConfigFile {
 
 impl ConfigFile {
     pub fn find() -> anyhow::Result<ConfigFile> {
-        let current_dir = std::env::current_dir()?;
-        match find_config(current_dir) {
+        ConfigFile::find_from(std::env::current_dir()?)
+    }
+
+    /// Finds the configuration file in `dir` or the nearest parent directory.
+    pub fn find_from(dir: PathBuf) -> anyhow::Result<ConfigFile> {
+        match find_config(dir) {
             Some(path) => {
                 let file = std::fs::File::open(&path)?;
                 let reader = std::io::BufReader::new(file);
-                let config = serde_yaml::from_reader(reader)?;
+                let mut config: Config = serde_yaml::from_reader(reader)?;
+                if let Some(dir) = path.parent() {
+                    config.resolve_manifests(dir);
+                }
                 Ok(ConfigFile {
                     config: config,
                     path: path,
@@ -1,26 +1,31 @@
 use super::aws;
 use super::config::{Auth, AuthConfig, Cluster, ClusterConfig, Config, Context, Release};
 use super::kubeconfig_writer;
+use super::runner::CommandRunner;
 use kube::config::{
     AuthInfo, ExecConfig, Kubeconfig, KubeconfigError, NamedAuthInfo, NamedCluster,
 };
 use std::collections::HashMap;
 
-pub fn prepare(config: &Config, release: &Release) -> anyhow::Result<()> {
+pub fn prepare(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    release: &Release,
+) -> anyhow::Result<()> {
     let context = config.find_context(&release)?;
     let auth = config.find_auth(context)?;
     let cluster = config.find_cluster(context)?;
     let kubeauth = build_auth(context, auth, cluster);
-    let kubecluster = build_cluster(cluster, auth)?;
+    let kubecluster = build_cluster(runner, cluster, auth)?;
 
     let kubeconfig = read_kubeconfig()?;
     log::debug!(
         "Loaded Kubernetes configuration successfully: {:?}",
         kubeconfig
     );
-    ensure_auth(&kubeconfig, kubeauth)?;
-    ensure_cluster(&kubeconfig, kubecluster)?;
-    ensure_context(&kubeconfig, &context)
+    ensure_auth(runner, &kubeconfig, kubeauth)?;
+    ensure_cluster(runner, &kubeconfig, kubecluster)?;
+    ensure_context(runner, &kubeconfig, &context)
 }
 
 fn read_kubeconfig() -> anyhow::Result<Kubeconfig> {
@@ -45,7 +50,11 @@ fn empty_config() -> Kubeconfig {
     }
 }
 
-fn ensure_context(config: &Kubeconfig, expected: &Context) -> anyhow::Result<()> {
+fn ensure_context(
+    runner: &dyn CommandRunner,
+    config: &Kubeconfig,
+    expected: &Context,
+) -> anyhow::Result<()> {
     log::debug!("Checking Kubernetes context {}", &expected.name);
 
     let exists = config
@@ -70,6 +79,7 @@ fn ensure_context(config: &Kubeconfig, expected: &Context) -> anyhow::Result<()>
     } else {
         log::info!("Writing Kubernetes context: {}", expected.name);
         kubeconfig_writer::write_context(
+            runner,
             &expected.name,
             &expected.name,
             &expected.cluster,
@@ -78,7 +88,11 @@ fn ensure_context(config: &Kubeconfig, expected: &Context) -> anyhow::Result<()>
     }
 }
 
-fn ensure_auth(config: &Kubeconfig, expected: NamedAuthInfo) -> anyhow::Result<()> {
+fn ensure_auth(
+    runner: &dyn CommandRunner,
+    config: &Kubeconfig,
+    expected: NamedAuthInfo,
+) -> anyhow::Result<()> {
     log::debug!("Checking Kubernetes credentials for {}", &expected.name);
 
     let exists =
@@ -108,7 +122,7 @@ fn ensure_auth(config: &Kubeconfig, expected: NamedAuthInfo) -> anyhow::Result<(
         Ok(())
     } else {
         log::info!("Writing Kubernetes credentials for {}", &expected.name);
-        kubeconfig_writer::write_auth(expected)
+        kubeconfig_writer::write_auth(runner, expected)
     }
 }
 
@@ -168,7 +182,11 @@ fn build_auth(context: &Context, auth: &Auth, cluster: &Cluster) -> NamedAuthInf
     }
 }
 
-fn ensure_cluster(config: &Kubeconfig, expected: NamedCluster) -> anyhow::Result<()> {
+fn ensure_cluster(
+    runner: &dyn CommandRunner,
+    config: &Kubeconfig,
+    expected: NamedCluster,
+) -> anyhow::Result<()> {
     log::debug!("Checking for Kubernetes cluster");
 
     let exists = config
@@ -193,11 +211,15 @@ fn ensure_cluster(config: &Kubeconfig, expected: NamedCluster) -> anyhow::Result
         Ok(())
     } else {
         log::info!("Writing cluster {}", &expected.name);
-        kubeconfig_writer::write_cluster(expected)
+        kubeconfig_writer::write_cluster(runner, expected)
     }
 }
 
-fn build_cluster(cluster: &Cluster, auth: &Auth) -> anyhow::Result<NamedCluster> {
+fn build_cluster(
+    runner: &dyn CommandRunner,
+    cluster: &Cluster,
+    auth: &Auth,
+) -> anyhow::Result<NamedCluster> {
     match &cluster.config {
         ClusterConfig::Eks { name, region } => {
             log::debug!(
@@ -206,7 +228,7 @@ fn build_cluster(cluster: &Cluster, auth: &Auth) -> anyhow::Result<NamedCluster>
                 auth.name,
                 region
             );
-            let eks_cluster = aws::get_eks_cluster(&auth.name, region, name)?;
+            let eks_cluster = aws::get_eks_cluster(runner, &auth.name, region, name)?;
             Ok(NamedCluster {
                 name: cluster.name.clone(),
                 cluster: Some(kube::config::Cluster {
@@ -0,0 +1,465 @@
+use super::podspec;
+use k8s_openapi::api::batch::v1 as batch;
+use k8s_openapi::api::core::v1 as k8s;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+const IMAGE_PULL_REASONS: [&str; 4] = [
+    "ErrImagePull",
+    "ImagePullBackOff",
+    "InvalidImageName",
+    "ErrImageNeverPull",
+];
+const CREATE_REASONS: [&str; 2] = ["CreateContainerConfigError", "CreateContainerError"];
+
+/// A problem with a pod, explained in plain language with the evidence
+/// which points to it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    pub pod: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+
+    /// A short name for the problem, such as `OOMKilled`.
+    pub problem: String,
+    pub explanation: String,
+    pub evidence: Vec<String>,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.problem, self.explanation)?;
+        for evidence in &self.evidence {
+            write!(f, "\n  {}", evidence)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists containers which have stopped unexpectedly, whose logs usually
+/// explain why, with whether those are the logs of their previous instance.
+/// Containers waiting to restart logged the reason before restarting, while
+/// those which won't restart logged it in their current instance.
+pub fn crashed_containers(pod: &k8s::Pod) -> Vec<(String, bool)> {
+    container_statuses(pod)
+        .filter_map(|status| {
+            let state = status.state.as_ref()?;
+            if state.waiting.is_some() && status.restart_count > 0 {
+                Some((status.name.clone(), true))
+            } else if state
+                .terminated
+                .as_ref()
+                .is_some_and(|terminated| terminated.exit_code != 0)
+            {
+                Some((status.name.clone(), false))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Explains why a pod can't start its containers, if it's stuck in a way
+/// which waiting won't fix, such as being unschedulable or failing to pull
+/// its image.
+pub fn stuck(pod: &k8s::Pod) -> Option<Finding> {
+    diagnose(pod, &[], &HashMap::new())
+        .into_iter()
+        .find(|finding| {
+            let problem = finding.problem.as_str();
+            problem == "Unschedulable"
+                || IMAGE_PULL_REASONS.contains(&problem)
+                || CREATE_REASONS.contains(&problem)
+        })
+}
+
+/// Returns whether a container has started, with whether it's still running,
+/// or why its pod can't start it. Containers which exit quickly may have
+/// finished before they're seen running.
+pub fn container_started(pod: &k8s::Pod, container: &str) -> Option<Result<bool, Finding>> {
+    if podspec::is_running(pod, container) {
+        Some(Ok(true))
+    } else if podspec::exit_code(pod, container).is_some() {
+        Some(Ok(false))
+    } else {
+        stuck(pod).map(Err)
+    }
+}
+
+/// Returns whether a job has finished, with the reason it failed if it did.
+pub fn job_finished(job: &batch::Job) -> Option<Result<(), String>> {
+    let conditions = job.status.as_ref()?.conditions.as_ref()?;
+    conditions
+        .iter()
+        .filter(|condition| condition.status == "True")
+        .find_map(|condition| match condition.type_.as_str() {
+            "Complete" => Some(Ok(())),
+            "Failed" => Some(Err(condition
+                .message
+                .clone()
+                .or_else(|| condition.reason.clone())
+                .unwrap_or_else(|| String::from("no reason given")))),
+            _ => None,
+        })
+}
+
+/// Explains what's wrong with a pod, if anything, using its status, the
+/// events about it, and the last log lines of crashed containers.
+pub fn diagnose(
+    pod: &k8s::Pod,
+    events: &[k8s::Event],
+    logs: &HashMap<String, Vec<String>>,
+) -> Vec<Finding> {
+    let name = pod.metadata.name.clone().unwrap_or_default();
+    let events: Vec<&k8s::Event> = events
+        .iter()
+        .filter(|event| {
+            event.involved_object.kind.as_deref() == Some("Pod")
+                && event.involved_object.name.as_ref() == Some(&name)
+        })
+        .collect();
+    let finding = |container: Option<&str>, problem: &str, explanation: String| Finding {
+        pod: name.clone(),
+        container: container.map(String::from),
+        problem: String::from(problem),
+        explanation,
+        evidence: Vec::new(),
+    };
+    let mut findings = Vec::new();
+
+    if let Some(condition) = unschedulable(pod) {
+        let mut unschedulable = finding(
+            None,
+            "Unschedulable",
+            String::from(
+                "The pod is pending because no node can run it, usually because it requests \
+                 more CPU or memory than any node has free, or its node selectors and \
+                 tolerations don't match a node.",
+            ),
+        );
+        unschedulable.evidence.extend(condition.message.clone());
+        unschedulable
+            .evidence
+            .extend(event_messages(&events, |reason| {
+                reason == "FailedScheduling"
+            }));
+        unschedulable.evidence.extend(requests(pod));
+        findings.push(unschedulable);
+    }
+
+    for status in container_statuses(pod) {
+        let container = Some(status.name.as_str());
+        let waiting = status
+            .state
+            .as_ref()
+            .and_then(|state| state.waiting.as_ref());
+        let waiting_reason = waiting.and_then(|waiting| waiting.reason.as_deref());
+        let spec = container_spec(pod, &status.name);
+
+        if let Some(reason) = waiting_reason.filter(|reason| IMAGE_PULL_REASONS.contains(reason)) {
+            let mut pull = finding(
+                container,
+                reason,
+                format!(
+                    "Kubernetes can't pull the image {} for container {}. Check that the tag \
+                     exists and that the cluster can access the registry.",
+                    spec.and_then(|spec| spec.image.as_deref())
+                        .unwrap_or(&status.image),
+                    status.name
+                ),
+            );
+            pull.evidence
+                .extend(waiting.and_then(|waiting| waiting.message.clone()));
+            pull.evidence.extend(event_messages(&events, |reason| {
+                reason == "Failed" || reason == "BackOff"
+            }));
+            findings.push(pull);
+            continue;
+        }
+
+        if let Some(reason) = waiting_reason.filter(|reason| CREATE_REASONS.contains(reason)) {
+            let mut create = finding(
+                container,
+                reason,
+                format!(
+                    "Container {} can't be created, often because a ConfigMap or Secret it \
+                     uses is missing.",
+                    status.name
+                ),
+            );
+            create
+                .evidence
+                .extend(waiting.and_then(|waiting| waiting.message.clone()));
+            findings.push(create);
+            continue;
+        }
+
+        let stopped = terminated(status);
+        if stopped.and_then(|stopped| stopped.reason.as_deref()) == Some("OOMKilled") {
+            let mut oom = finding(
+                container,
+                "OOMKilled",
+                format!(
+                    "Container {} was killed for using more memory than it's allowed. Raise \
+                     its memory limit or reduce its memory use.",
+                    status.name
+                ),
+            );
+            oom.evidence.push(match memory_limit(spec) {
+                Some(limit) => format!("Memory limit: {}", limit),
+                None => String::from("No memory limit; the node itself ran out of memory"),
+            });
+            oom.evidence.extend(restarts(status));
+            oom.evidence.extend(log_lines(logs, &status.name));
+            findings.push(oom);
+        } else if waiting_reason == Some("CrashLoopBackOff") {
+            let mut crash = finding(
+                container,
+                "CrashLoopBackOff",
+                format!(
+                    "Container {} keeps exiting, so Kubernetes waits longer before each \
+                     restart. Its last log lines usually show why.",
+                    status.name
+                ),
+            );
+            crash.evidence.extend(stopped.map(|stopped| {
+                format!(
+                    "Last exited with code {} ({})",
+                    stopped.exit_code,
+                    stopped.reason.as_deref().unwrap_or("no reason given")
+                )
+            }));
+            crash.evidence.extend(restarts(status));
+            crash.evidence.extend(log_lines(logs, &status.name));
+            findings.push(crash);
+        }
+    }
+
+    for (probe, problem, explanation) in [
+        (
+            "Liveness",
+            "LivenessProbeFailed",
+            "Kubernetes restarts containers whose liveness probe fails.",
+        ),
+        (
+            "Readiness",
+            "ReadinessProbeFailed",
+            "The pod receives no traffic while its readiness probe fails.",
+        ),
+        (
+            "Startup",
+            "StartupProbeFailed",
+            "Kubernetes restarts containers which don't pass their startup probe in time.",
+        ),
+    ] {
+        let prefix = format!("{} probe failed", probe);
+        let messages: Vec<String> = events
+            .iter()
+            .filter(|event| event.reason.as_deref() == Some("Unhealthy"))
+            .filter(|event| {
+                event
+                    .message
+                    .as_deref()
+                    .is_some_and(|message| message.starts_with(&prefix))
+            })
+            .map(|event| event_message(event))
+            .collect();
+        if !messages.is_empty() {
+            let mut failed = finding(None, problem, String::from(explanation));
+            failed.evidence.extend(messages);
+            failed.evidence.extend(probes(pod, probe));
+            findings.push(failed);
+        }
+    }
+
+    if findings.is_empty() && is_running(pod) {
+        let not_ready: Vec<&str> = container_statuses(pod)
+            .filter(|status| !status.ready)
+            .map(|status| status.name.as_str())
+            .collect();
+        if !not_ready.is_empty() {
+            let mut unready = finding(
+                None,
+                "NotReady",
+                String::from("The pod is running but some containers aren't ready yet."),
+            );
+            unready
+                .evidence
+                .push(format!("Not ready: {}", not_ready.join(", ")));
+            findings.push(unready);
+        }
+    }
+
+    findings
+}
+
+fn container_statuses(pod: &k8s::Pod) -> impl Iterator<Item = &k8s::ContainerStatus> {
+    pod.status.iter().flat_map(|status| {
+        status
+            .init_container_statuses
+            .iter()
+            .flatten()
+            .chain(status.container_statuses.iter().flatten())
+    })
+}
+
+fn container_spec<'p>(pod: &'p k8s::Pod, name: &str) -> Option<&'p k8s::Container> {
+    pod.spec.iter().find_map(|spec| {
+        spec.init_containers
+            .iter()
+            .flatten()
+            .chain(&spec.containers)
+            .find(|container| container.name == name)
+    })
+}
+
+/// The current termination if the container is stopped, otherwise the last.
+fn terminated(status: &k8s::ContainerStatus) -> Option<&k8s::ContainerStateTerminated> {
+    status
+        .state
+        .as_ref()
+        .and_then(|state| state.terminated.as_ref())
+        .or_else(|| {
+            status
+                .last_state
+                .as_ref()
+                .and_then(|state| state.terminated.as_ref())
+        })
+}
+
+fn unschedulable(pod: &k8s::Pod) -> Option<&k8s::PodCondition> {
+    pod.status
+        .as_ref()?
+        .conditions
+        .as_ref()?
+        .iter()
+        .find(|condition| {
+            condition.type_ == "PodScheduled"
+                && condition.status == "False"
+                && condition.reason.as_deref() == Some("Unschedulable")
+        })
+}
+
+fn is_running(pod: &k8s::Pod) -> bool {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.phase.as_deref())
+        == Some("Running")
+        && pod.metadata.deletion_timestamp.is_none()
+}
+
+fn event_messages<F>(events: &[&k8s::Event], reason: F) -> Vec<String>
+where
+    F: Fn(&str) -> bool,
+{
+    events
+        .iter()
+        .filter(|event| event.reason.as_deref().is_some_and(&reason))
+        .map(|event| event_message(event))
+        .collect()
+}
+
+fn event_message(event: &k8s::Event) -> String {
+    let message = event.message.as_deref().unwrap_or_default().trim();
+    match event.count {
+        Some(count) if count > 1 => format!("Event: {} (seen {} times)", message, count),
+        _ => format!("Event: {}", message),
+    }
+}
+
+fn requests(pod: &k8s::Pod) -> Vec<String> {
+    pod.spec
+        .iter()
+        .flat_map(|spec| &spec.containers)
+        .filter_map(|container| {
+            let requests = container.resources.as_ref()?.requests.as_ref()?;
+            let requested: Vec<String> = requests
+                .iter()
+                .map(|(resource, quantity)| format!("{} {}", resource, quantity.0))
+                .collect();
+            Some(format!(
+                "Container {} requests {}",
+                container.name,
+                requested.join(", ")
+            ))
+        })
+        .collect()
+}
+
+fn memory_limit(container: Option<&k8s::Container>) -> Option<&str> {
+    container?
+        .resources
+        .as_ref()?
+        .limits
+        .as_ref()?
+        .get("memory")
+        .map(|quantity| quantity.0.as_str())
+}
+
+fn restarts(status: &k8s::ContainerStatus) -> Option<String> {
+    match status.restart_count {
+        0 => None,
+        1 => Some(String::from("Restarted once")),
+        count => Some(format!("Restarted {} times", count)),
+    }
+}
+
+fn log_lines(logs: &HashMap<String, Vec<String>>, container: &str) -> Vec<String> {
+    match logs.get(container) {
+        Some(lines) if !lines.is_empty() => std::iter::once(format!(
+            "Last log lines from {} before it stopped:",
+            container
+        ))
+        .chain(lines.iter().map(|line| format!("  | {}", line)))
+        .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn probes(pod: &k8s::Pod, kind: &str) -> Vec<String> {
+    pod.spec
+        .iter()
+        .flat_map(|spec| &spec.containers)
+        .filter_map(|container| {
+            let probe = match kind {
+                "Liveness" => container.liveness_probe.as_ref(),
+                "Readiness" => container.readiness_probe.as_ref(),
+                _ => container.startup_probe.as_ref(),
+            }?;
+            Some(format!(
+                "Container {} checks {} every {}s, timing out after {}s",
+                container.name,
+                probe_target(probe),
+                probe.period_seconds.unwrap_or(10),
+                probe.timeout_seconds.unwrap_or(1)
+            ))
+        })
+        .collect()
+}
+
+fn probe_target(probe: &k8s::Probe) -> String {
+    let port = |port: &IntOrString| match port {
+        IntOrString::Int(port) => port.to_string(),
+        IntOrString::String(port) => port.clone(),
+    };
+    if let Some(http) = &probe.http_get {
+        format!(
+            "HTTP {} on port {}",
+            http.path.as_deref().unwrap_or("/"),
+            port(&http.port)
+        )
+    } else if let Some(tcp) = &probe.tcp_socket {
+        format!("TCP port {}", port(&tcp.port))
+    } else if let Some(exec) = &probe.exec {
+        format!(
+            "the command `{}`",
+            exec.command.as_deref().unwrap_or_default().join(" ")
+        )
+    } else {
+        String::from("a probe")
+    }
+}
@@ -0,0 +1,155 @@
+use super::workloads::Resources;
+use k8s_openapi::api::apps::v1 as apps;
+use k8s_openapi::api::batch::v1 as batch;
+use k8s_openapi::api::core::v1 as k8s;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+use k8s_openapi::{Metadata, Resource};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// An event about one of the objects running for a release.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+    /// The event's own name, which stays the same as it repeats.
+    pub name: String,
+    pub time: Option<Time>,
+
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub reason: String,
+
+    /// The object the event is about, such as `pod/example-web-5d4f-b`.
+    pub object: String,
+    pub message: String,
+    pub count: i32,
+}
+
+impl Record {
+    pub fn is_warning(&self) -> bool {
+        self.type_ == "Warning"
+    }
+}
+
+/// The kinds of objects whose events are shown for a release.
+pub const KINDS: [&str; 6] = [
+    apps::Deployment::KIND,
+    apps::StatefulSet::KIND,
+    batch::CronJob::KIND,
+    apps::ReplicaSet::KIND,
+    batch::Job::KIND,
+    k8s::Pod::KIND,
+];
+
+/// Picks the events about the given resources, oldest first. Events don't
+/// have the labels of the objects they're about, so they're matched by kind
+/// and name.
+pub fn select(events: Vec<k8s::Event>, resources: &Resources, warnings_only: bool) -> Vec<Record> {
+    let owned = owned_objects(resources);
+    let mut records: Vec<Record> = events
+        .into_iter()
+        .filter(|event| {
+            let object = &event.involved_object;
+            match (&object.kind, &object.name) {
+                (Some(kind), Some(name)) => {
+                    owned.contains(&(kind.as_str(), name.as_str()))
+                        || generated(resources, kind, name)
+                }
+                _ => false,
+            }
+        })
+        .map(record)
+        .filter(|record| !warnings_only || record.is_warning())
+        .collect();
+    records.sort_by(|a, b| {
+        let time = |record: &Record| record.time.as_ref().map(|Time(time)| *time);
+        time(a).cmp(&time(b)).then_with(|| a.name.cmp(&b.name))
+    });
+    records
+}
+
+fn names<K: Metadata<Ty = ObjectMeta>>(objects: &[K]) -> impl Iterator<Item = &str> {
+    objects
+        .iter()
+        .filter_map(|object| object.metadata().name.as_deref())
+}
+
+fn owned_objects(resources: &Resources) -> HashSet<(&'static str, &str)> {
+    fn named<K: Metadata<Ty = ObjectMeta>>(
+        objects: &[K],
+    ) -> impl Iterator<Item = (&'static str, &str)> {
+        names(objects).map(|name| (K::KIND, name))
+    }
+
+    named(&resources.deployments)
+        .chain(named(&resources.stateful_sets))
+        .chain(named(&resources.cron_jobs))
+        .chain(named(&resources.replica_sets))
+        .chain(named(&resources.jobs))
+        .chain(named(&resources.pods))
+        .collect()
+}
+
+/// Whether an object could have been created by one of the resources, such
+/// as a pod replaced during a rollout, whose events outlive it. Owners name
+/// the objects they create by adding a suffix, so a Deployment's pods have
+/// two: one from their ReplicaSet and one of their own.
+fn generated(resources: &Resources, kind: &str, name: &str) -> bool {
+    let owners: Vec<(&str, usize)> = match kind {
+        "Pod" => names(&resources.deployments)
+            .chain(names(&resources.cron_jobs))
+            .map(|owner| (owner, 2))
+            .chain(
+                names(&resources.stateful_sets)
+                    .chain(names(&resources.replica_sets))
+                    .chain(names(&resources.jobs))
+                    .map(|owner| (owner, 1)),
+            )
+            .collect(),
+        "ReplicaSet" => names(&resources.deployments)
+            .map(|owner| (owner, 1))
+            .collect(),
+        "Job" => names(&resources.cron_jobs)
+            .map(|owner| (owner, 1))
+            .collect(),
+        _ => Vec::new(),
+    };
+    owners.into_iter().any(|(owner, suffixes)| {
+        name.strip_prefix(owner)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .is_some_and(|rest| {
+                let parts: Vec<&str> = rest.split('-').collect();
+                parts.len() == suffixes
+                    && parts.iter().all(|part| {
+                        !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric())
+                    })
+            })
+    })
+}
+
+fn record(event: k8s::Event) -> Record {
+    // Older clients only set the timestamps, while newer ones only set the
+    // event time.
+    let time = event
+        .last_timestamp
+        .or_else(|| event.event_time.map(|time| Time(time.0)))
+        .or(event.first_timestamp)
+        .or(event.metadata.creation_timestamp);
+    let object = &event.involved_object;
+    Record {
+        name: event.metadata.name.unwrap_or_default(),
+        time,
+        type_: event.type_.unwrap_or_else(|| String::from("Normal")),
+        reason: event.reason.unwrap_or_default(),
+        object: format!(
+            "{}/{}",
+            object.kind.as_deref().unwrap_or_default().to_lowercase(),
+            object.name.as_deref().unwrap_or_default()
+        ),
+        message: event
+            .message
+            .map(|message| message.trim().to_string())
+            .unwrap_or_default(),
+        count: event.count.unwrap_or(1),
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+#[cfg(unix)]
+use tokio::signal::unix::{signal, Signal, SignalKind};
+
+#[cfg(not(unix))]
+use futures::future::{BoxFuture, FutureExt};
+
+/// Catches Ctrl-C so that flightctl can clean up before exiting.
+///
+/// Once interrupts are caught they no longer stop flightctl, but programs it
+/// runs in the foreground, such as `psql`, still receive them.
+pub struct Interrupts {
+    runtime: Runtime,
+    signal: Listener,
+}
+
+#[cfg(unix)]
+type Listener = Signal;
+
+/// Other platforms only offer a future for the next Ctrl-C, which starts
+/// catching interrupts when first polled.
+#[cfg(not(unix))]
+type Listener = BoxFuture<'static, std::io::Result<()>>;
+
+impl fmt::Debug for Interrupts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Interrupts").finish_non_exhaustive()
+    }
+}
+
+impl Interrupts {
+    pub fn catch() -> anyhow::Result<Interrupts> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let signal = listen(&runtime)?;
+        Ok(Interrupts { runtime, signal })
+    }
+
+    /// Waits up to `timeout` for an interrupt, returning whether one arrived.
+    #[cfg(unix)]
+    pub fn wait(&mut self, timeout: Duration) -> bool {
+        let signal = &mut self.signal;
+        self.runtime
+            .block_on(async { tokio::time::timeout(timeout, signal.recv()).await })
+            .is_ok()
+    }
+
+    /// Waits up to `timeout` for an interrupt, returning whether one arrived.
+    #[cfg(not(unix))]
+    pub fn wait(&mut self, timeout: Duration) -> bool {
+        let signal = &mut self.signal;
+        let interrupted = self
+            .runtime
+            .block_on(async { tokio::time::timeout(timeout, signal).await })
+            .is_ok();
+        if interrupted {
+            // The future is finished, so listen for the next interrupt.
+            match listen(&self.runtime) {
+                Ok(signal) => self.signal = signal,
+                Err(err) => log::warn!("Couldn't catch interrupts: {}", err),
+            }
+        }
+        interrupted
+    }
+}
+
+#[cfg(unix)]
+fn listen(runtime: &Runtime) -> anyhow::Result<Listener> {
+    let _context = runtime.enter();
+    Ok(signal(SignalKind::interrupt())?)
+}
+
+#[cfg(not(unix))]
+fn listen(runtime: &Runtime) -> anyhow::Result<Listener> {
+    let mut signal = tokio::signal::ctrl_c().boxed();
+    runtime.block_on(async { (&mut signal).now_or_never() });
+    Ok(signal)
+}
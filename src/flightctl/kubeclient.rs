@@ -1,10 +1,38 @@
+use super::diagnosis;
 use super::kubectl;
+use super::podspec;
+use super::runner::{CommandRunner, Output, Process};
+use futures::stream::BoxStream;
+use futures::{AsyncBufReadExt, StreamExt, TryStreamExt};
+use k8s_openapi::api::apps::v1 as apps;
+use k8s_openapi::api::batch::v1 as batch;
 use k8s_openapi::api::core::v1 as k8s;
-use std::collections::HashMap;
+use k8s_openapi::NamespaceResourceScope;
+use kube::api::{Api, ListParams, LogParams, WatchEvent};
+use kube::config::KubeConfigOptions;
+use kube::Resource;
+use serde::de::DeserializeOwned;
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
 
 #[derive(Debug)]
-pub struct KubeClient {
+pub struct KubeClient<'r> {
+    runner: &'r dyn CommandRunner,
     context: String,
+    connection: OnceCell<Connection>,
+}
+
+#[derive(Debug)]
+pub struct LogTarget {
+    pub pod: String,
+    pub container: String,
 }
 
 #[derive(Debug)]
@@ -12,59 +40,163 @@ pub struct Selector {
     labels: HashMap<String, String>,
 }
 
-pub fn new(context: &str) -> KubeClient {
+struct Connection {
+    client: kube::Client,
+    runtime: Runtime,
+}
+
+impl fmt::Debug for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Connection").finish_non_exhaustive()
+    }
+}
+
+/// Changes to resources of one kind, as reported by the API server.
+pub type WatchStream<K> = BoxStream<'static, anyhow::Result<WatchEvent<K>>>;
+
+pub fn new<'r>(runner: &'r dyn CommandRunner, context: &str) -> KubeClient<'r> {
     KubeClient {
+        runner,
         context: String::from(context),
+        connection: OnceCell::new(),
     }
 }
 
-impl KubeClient {
+impl<'r> KubeClient<'r> {
     pub fn get_available_pod(&self, selector: Selector) -> anyhow::Result<k8s::Pod> {
-        let output = kubectl::run_get_output(&[
-            "--context",
-            &self.context,
-            "get",
-            "pod",
-            "--selector",
-            &selector.to_string(),
-            "--field-selector",
-            "status.phase=Running",
-            "--output",
-            "name",
-        ])?;
-        let pod_names = String::from_utf8(output.stdout)?;
-        let pod_name = pod_names
-            .split_whitespace()
-            .nth(0)
-            .ok_or(anyhow::Error::msg("No console pod found"))?;
-        let pod = self.fetch_resource(&pod_name.trim_end())?;
-        Ok(pod)
-    }
-
-    pub fn get_workloads(&self, selector: Selector) -> anyhow::Result<()> {
-        kubectl::run_print(&[
-            "--context",
-            &self.context,
-            "get",
-            "deploy",
-            "--selector",
-            &selector.to_string(),
-        ])
+        self.find_available_pod(&selector)?
+            .ok_or(anyhow::Error::msg("No console pod found"))
     }
 
-    pub fn exec<S>(&self, pod: &k8s::Pod, container: &str, command: &Vec<S>) -> anyhow::Result<()>
+    pub fn find_available_pod(&self, selector: &Selector) -> anyhow::Result<Option<k8s::Pod>> {
+        let params = ListParams::default()
+            .labels(&selector.to_string())
+            .fields("status.phase=Running");
+        let (runtime, api) = self.namespaced::<k8s::Pod>()?;
+        log::debug!("Listing pods matching {:?}", &params);
+        let pods = runtime.block_on(api.list(&params))?;
+        Ok(pods.items.into_iter().next())
+    }
+
+    /// Builds a pod from the template of a Deployment matching `workloads`
+    /// whose pods would match `pods`, for inspecting releases which have no
+    /// running pods. The pod has no name or status.
+    pub fn get_pod_template(
+        &self,
+        workloads: &Selector,
+        pods: &Selector,
+    ) -> anyhow::Result<(String, k8s::Pod)> {
+        let deployment = self
+            .list_resources::<apps::Deployment>(workloads)?
+            .into_iter()
+            .find(|deployment| {
+                let labels = deployment
+                    .spec
+                    .as_ref()
+                    .and_then(|spec| spec.template.metadata.as_ref())
+                    .and_then(|metadata| metadata.labels.as_ref());
+                pods.matches(labels)
+            })
+            .ok_or(anyhow::anyhow!(
+                "No deployment found with pods matching {}",
+                pods.to_string()
+            ))?;
+        Ok(podspec::template_pod(deployment))
+    }
+
+    pub fn get_workloads(&self, selector: Selector) -> anyhow::Result<Vec<apps::Deployment>> {
+        self.list_resources(&selector)
+    }
+
+    pub fn stream_logs<F>(
+        &self,
+        targets: &[LogTarget],
+        params: &LogParams,
+        mut on_line: F,
+    ) -> anyhow::Result<()>
+    where
+        F: FnMut(&LogTarget, &str) -> anyhow::Result<()>,
+    {
+        let (runtime, api) = self.namespaced::<k8s::Pod>()?;
+        runtime.block_on(async {
+            let mut streams = Vec::new();
+            for target in targets {
+                let target_params = LogParams {
+                    container: Some(target.container.clone()),
+                    ..params.clone()
+                };
+                log::debug!("Streaming logs for {:?}", target);
+                let stream = match api.log_stream(&target.pod, &target_params).await {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        log::warn!(
+                            "Couldn't stream logs from {}/{}: {}; skipping",
+                            target.pod,
+                            target.container,
+                            err
+                        );
+                        continue;
+                    }
+                };
+                let lines = stream
+                    .map_err(io::Error::other)
+                    .into_async_read()
+                    .lines()
+                    .map(move |line| line.map(|line| (target, line)));
+                streams.push(Box::pin(lines));
+            }
+
+            if streams.is_empty() {
+                return Err(anyhow::anyhow!("Couldn't stream logs from any container"));
+            }
+
+            let mut merged = futures::stream::select_all(streams);
+            while let Some(result) = merged.next().await {
+                let (target, line) = result?;
+                on_line(target, &line)?;
+            }
+            anyhow::Ok(())
+        })
+    }
+
+    /// Reads the last lines a container logged, from its previous instance
+    /// if `previous` is set, such as before it was restarted.
+    pub fn last_logs(
+        &self,
+        pod: &str,
+        container: &str,
+        previous: bool,
+        lines: i64,
+    ) -> anyhow::Result<Vec<String>> {
+        let (runtime, api) = self.namespaced::<k8s::Pod>()?;
+        let params = LogParams {
+            container: Some(String::from(container)),
+            previous,
+            tail_lines: Some(lines),
+            ..LogParams::default()
+        };
+        log::debug!("Fetching last logs for {} in {}", container, pod);
+        let logs = runtime.block_on(api.logs(pod, &params))?;
+        Ok(logs.lines().map(String::from).collect())
+    }
+
+    pub fn exec<S>(
+        &self,
+        pod: &k8s::Pod,
+        container: &str,
+        command: &[S],
+        tty: bool,
+    ) -> anyhow::Result<()>
     where
         S: AsRef<str>,
     {
         let pod_name = pod.metadata.name.as_deref();
         kubectl::run_print(
+            self.runner,
             &[
+                vec!["--context", &self.context, "exec"],
+                terminal_flags(tty).to_vec(),
                 vec![
-                    "--context",
-                    &self.context,
-                    "exec",
-                    "--stdin",
-                    "--tty",
                     &pod_name.unwrap_or_default(),
                     "--container",
                     container,
@@ -76,11 +208,368 @@ impl KubeClient {
         )
     }
 
+    /// Writes a file into a container by streaming `input` to `cat`.
+    pub fn upload(
+        &self,
+        pod: &k8s::Pod,
+        container: &str,
+        path: &str,
+        input: &mut (dyn Read + Send),
+    ) -> anyhow::Result<()> {
+        self.exec_input(pod, container, &["sh", "-c", "cat > \"$0\"", path], input)
+    }
+
+    pub fn remove_file(&self, pod: &k8s::Pod, container: &str, path: &str) -> anyhow::Result<()> {
+        self.exec_output(pod, container, &["rm", "-f", path])?;
+        Ok(())
+    }
+
+    /// Runs a command in a container without a terminal, capturing its
+    /// output.
+    pub fn exec_output(
+        &self,
+        pod: &k8s::Pod,
+        container: &str,
+        command: &[&str],
+    ) -> anyhow::Result<Output> {
+        kubectl::run_get_output(self.runner, &self.exec_args(pod, container, false, command))
+    }
+
+    /// Runs a command in a container, streaming `input` to its stdin.
+    pub fn exec_input(
+        &self,
+        pod: &k8s::Pod,
+        container: &str,
+        command: &[&str],
+        input: &mut (dyn Read + Send),
+    ) -> anyhow::Result<()> {
+        kubectl::run_with_input(
+            self.runner,
+            &self.exec_args(pod, container, true, command),
+            input,
+        )?;
+        Ok(())
+    }
+
+    /// Runs a command in a container, streaming its stdout to `output`.
+    pub fn exec_to(
+        &self,
+        pod: &k8s::Pod,
+        container: &str,
+        command: &[&str],
+        output: &mut (dyn Write + Send),
+    ) -> anyhow::Result<()> {
+        kubectl::run_to(
+            self.runner,
+            &self.exec_args(pod, container, false, command),
+            output,
+        )
+    }
+
+    /// Streams logs from the first pod of a resource such as `job/migrate`,
+    /// waiting for it to start.
+    pub fn follow_logs(
+        &self,
+        resource: &str,
+        container: &str,
+        start_timeout: Duration,
+    ) -> anyhow::Result<()> {
+        kubectl::run_print(
+            self.runner,
+            &[
+                "--context",
+                &self.context,
+                "logs",
+                "--follow",
+                resource,
+                "--container",
+                container,
+                &format!("--pod-running-timeout={}s", start_timeout.as_secs()),
+            ],
+        )
+    }
+
+    /// Waits for a job's container to terminate, returning its exit code.
+    /// Fails early if the job fails or its pod can't start.
+    pub fn wait_for_exit(
+        &self,
+        job: &str,
+        container: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<i32> {
+        let selector = Selector::new(HashMap::from([(
+            String::from("job-name"),
+            String::from(job),
+        )]));
+        let deadline = Instant::now() + timeout;
+        loop {
+            let pods: Vec<k8s::Pod> = self.list_resources(&selector)?;
+            if let Some(code) = pods
+                .iter()
+                .find_map(|pod| podspec::exit_code(pod, container))
+            {
+                return Ok(code);
+            }
+            match diagnosis::job_finished(&self.fetch_resource::<batch::Job>(job)?) {
+                // The pod may already have been removed.
+                Some(Ok(())) => return Ok(0),
+                Some(Err(reason)) => return Err(anyhow::anyhow!("Job {} failed: {}", job, reason)),
+                None => {}
+            }
+            if let Some(finding) = pods.iter().find_map(diagnosis::stuck) {
+                return Err(anyhow::anyhow!(
+                    "pod/{} can't start: {}",
+                    finding.pod,
+                    finding
+                ));
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow::anyhow!(
+                    "Timed out waiting for {} to finish in job {}",
+                    container,
+                    job
+                ));
+            }
+            thread::sleep(Duration::from_secs(2));
+        }
+    }
+
+    /// Waits for a container in a pod such as `pod/web-console-x7k2q` to
+    /// start, returning whether it's still running. Containers which exit
+    /// quickly may have finished already. Fails early if the pod can't start.
+    pub fn wait_for_start(
+        &self,
+        resource: &str,
+        container: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<bool> {
+        let name = resource.rsplit('/').next().unwrap_or(resource);
+        let deadline = Instant::now() + timeout;
+        loop {
+            let pod: k8s::Pod = self.fetch_resource(name)?;
+            match diagnosis::container_started(&pod, container) {
+                Some(Ok(running)) => return Ok(running),
+                Some(Err(finding)) => {
+                    return Err(anyhow::anyhow!("{} can't start: {}", resource, finding))
+                }
+                None => {}
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow::anyhow!(
+                    "Timed out waiting for {} to start in {}",
+                    container,
+                    resource
+                ));
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+
+    pub fn attach(&self, resource: &str, container: &str, tty: bool) -> anyhow::Result<()> {
+        kubectl::run_print(
+            self.runner,
+            &[
+                vec!["--context", &self.context, "attach"],
+                terminal_flags(tty).to_vec(),
+                vec![resource, "--container", container],
+            ]
+            .concat(),
+        )
+    }
+
+    /// Creates resources from a manifest, returning their names, such as
+    /// `pod/web-console-x7k2q`.
+    pub fn create(&self, manifests: &Path) -> anyhow::Result<Vec<String>> {
+        let output = kubectl::run_get_output(
+            self.runner,
+            &[
+                "--context",
+                &self.context,
+                "create",
+                "--filename",
+                &manifests.to_string_lossy(),
+                "--output",
+                "name",
+            ],
+        )?;
+        let names = String::from_utf8(output.stdout)?;
+        Ok(names.lines().map(String::from).collect())
+    }
+
+    pub fn delete(&self, resource: &str) -> anyhow::Result<()> {
+        kubectl::run_get_output(
+            self.runner,
+            &[
+                "--context",
+                &self.context,
+                "delete",
+                resource,
+                "--wait=false",
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn wait(&self, resource: &str, condition: &str, timeout: Duration) -> anyhow::Result<()> {
+        kubectl::run_get_output(
+            self.runner,
+            &[
+                "--context",
+                &self.context,
+                "wait",
+                resource,
+                &format!("--for={}", condition),
+                "--timeout",
+                &format!("{}s", timeout.as_secs()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Attaches an ephemeral debug container to a pod, sharing the process
+    /// namespace of the `target` container.
+    pub fn debug<S>(
+        &self,
+        pod: &k8s::Pod,
+        target: &str,
+        image: &str,
+        command: &[S],
+        tty: bool,
+    ) -> anyhow::Result<()>
+    where
+        S: AsRef<str>,
+    {
+        let pod_name = pod.metadata.name.as_deref().unwrap_or_default();
+        let target = format!("--target={}", target);
+        let image = format!("--image={}", image);
+        let mut args = vec!["--context", &self.context, "debug"];
+        args.extend(terminal_flags(tty));
+        args.extend([pod_name, &target, &image]);
+        if !command.is_empty() {
+            args.push("--");
+            args.extend(command.iter().map(|s| s.as_ref()));
+        }
+        kubectl::run_print(self.runner, &args)
+    }
+
+    pub fn apply(&self, manifests: &Path) -> anyhow::Result<()> {
+        kubectl::run_print(
+            self.runner,
+            &[
+                "--context",
+                &self.context,
+                "apply",
+                "--filename",
+                &manifests.to_string_lossy(),
+            ],
+        )
+    }
+
+    /// Shows a server-side diff of manifests against live objects, returning
+    /// whether anything would change.
+    pub fn diff(&self, manifests: &Path) -> anyhow::Result<bool> {
+        let args = [
+            "--context",
+            &self.context,
+            "diff",
+            "--server-side",
+            "--force-conflicts",
+            "--filename",
+            &manifests.to_string_lossy(),
+        ];
+        let status = kubectl::run_status(self.runner, &args)?;
+        match status.code() {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => kubectl::verify_exit(&args, status).and(Ok(false)),
+        }
+    }
+
+    /// Waits for a resource to roll out. Resources outside the context's
+    /// namespace, such as those with a namespace set in their manifests, need
+    /// their `namespace` given.
+    pub fn rollout_status(
+        &self,
+        resource: &str,
+        namespace: Option<&str>,
+        timeout: &str,
+    ) -> anyhow::Result<()> {
+        let mut args = vec!["--context", &self.context];
+        if let Some(namespace) = namespace {
+            args.extend(["--namespace", namespace]);
+        }
+        args.extend(["rollout", "status", resource, "--timeout", timeout]);
+        kubectl::run_print(self.runner, &args)
+    }
+
+    fn exec_args<'a>(
+        &'a self,
+        pod: &'a k8s::Pod,
+        container: &'a str,
+        stdin: bool,
+        command: &[&'a str],
+    ) -> Vec<&'a str> {
+        let pod_name = pod.metadata.name.as_deref().unwrap_or_default();
+        let mut args = vec!["--context", &self.context, "exec"];
+        if stdin {
+            args.push("--stdin");
+        }
+        args.extend([pod_name, "--container", container, "--"]);
+        args.extend(command);
+        args
+    }
+
+    /// Starts forwarding a local port to a port on a resource, such as
+    /// `service/web`, until the returned process is stopped or the connection
+    /// is lost.
+    pub fn port_forward(
+        &self,
+        resource: &str,
+        local_port: u16,
+        remote_port: u16,
+    ) -> anyhow::Result<Box<dyn Process>> {
+        kubectl::spawn(
+            self.runner,
+            &[
+                "--context",
+                &self.context,
+                "port-forward",
+                resource,
+                &format!("{}:{}", local_port, remote_port),
+            ],
+        )
+    }
+
+    pub fn rollout_restart(&self, resource: &str) -> anyhow::Result<()> {
+        kubectl::run_print(
+            self.runner,
+            &["--context", &self.context, "rollout", "restart", resource],
+        )
+    }
+
+    /// Applies a JSON merge patch to a resource, such as `configmap/web`.
+    pub fn patch(&self, resource: &str, patch: &serde_json::Value) -> anyhow::Result<()> {
+        kubectl::run_print(
+            self.runner,
+            &[
+                "--context",
+                &self.context,
+                "patch",
+                resource,
+                "--type",
+                "merge",
+                "--patch",
+                &patch.to_string(),
+            ],
+        )
+    }
+
     pub fn run_command<S>(&self, command: &Vec<S>) -> anyhow::Result<()>
     where
         S: AsRef<str>,
     {
         kubectl::run_print(
+            self.runner,
             &[
                 vec!["--context", &self.context],
                 command.iter().map(|s| s.as_ref()).collect(),
@@ -89,21 +578,142 @@ impl KubeClient {
         )
     }
 
-    pub fn fetch_resource<T>(&self, resource: &str) -> anyhow::Result<T>
+    pub fn fetch_resource<K>(&self, name: &str) -> anyhow::Result<K>
     where
-        T: for<'de> serde::Deserialize<'de>,
+        K: Resource<Scope = NamespaceResourceScope> + Clone + DeserializeOwned + fmt::Debug,
+        K::DynamicType: Default,
     {
-        let output = kubectl::run_get_output(&[
-            "--context",
-            &self.context,
-            "get",
-            &resource,
-            "--output",
-            "yaml",
-        ])?;
-        let yaml = String::from_utf8(output.stdout)?;
-        let result = serde_yaml::from_str::<T>(&yaml)?;
-        Ok(result)
+        let (runtime, api) = self.namespaced::<K>()?;
+        log::debug!("Fetching {} {}", K::kind(&K::DynamicType::default()), name);
+        let resource = runtime.block_on(api.get(name))?;
+        Ok(resource)
+    }
+
+    pub fn list_resources<K>(&self, selector: &Selector) -> anyhow::Result<Vec<K>>
+    where
+        K: Resource<Scope = NamespaceResourceScope> + Clone + DeserializeOwned + fmt::Debug,
+        K::DynamicType: Default,
+    {
+        let params = ListParams::default().labels(&selector.to_string());
+        let (runtime, api) = self.namespaced::<K>()?;
+        log::debug!(
+            "Listing {} matching {:?}",
+            K::plural(&K::DynamicType::default()),
+            &params
+        );
+        let resources = runtime.block_on(api.list(&params))?;
+        Ok(resources.items)
+    }
+
+    /// Lists resources matching a selector, along with a stream of the
+    /// changes made to them after the list. The stream ends when the API
+    /// server closes the watch.
+    pub fn list_and_watch<K>(&self, selector: &Selector) -> anyhow::Result<(Vec<K>, WatchStream<K>)>
+    where
+        K: Resource<Scope = NamespaceResourceScope>
+            + Clone
+            + DeserializeOwned
+            + fmt::Debug
+            + Send
+            + 'static,
+        K::DynamicType: Default,
+    {
+        self.list_and_watch_with(ListParams::default().labels(&selector.to_string()))
+    }
+
+    /// Lists the events about objects of one kind, such as `Pod`.
+    pub fn list_events(&self, kind: &str) -> anyhow::Result<Vec<k8s::Event>> {
+        let params = ListParams::default().fields(&format!("involvedObject.kind={}", kind));
+        let (runtime, api) = self.namespaced::<k8s::Event>()?;
+        log::debug!("Listing events matching {:?}", &params);
+        let events = runtime.block_on(api.list(&params))?;
+        Ok(events.items)
+    }
+
+    /// Lists the events about objects of one kind, along with a stream of
+    /// new and updated events.
+    pub fn watch_events(
+        &self,
+        kind: &str,
+    ) -> anyhow::Result<(Vec<k8s::Event>, WatchStream<k8s::Event>)> {
+        self.list_and_watch_with(
+            ListParams::default().fields(&format!("involvedObject.kind={}", kind)),
+        )
+    }
+
+    fn list_and_watch_with<K>(&self, params: ListParams) -> anyhow::Result<(Vec<K>, WatchStream<K>)>
+    where
+        K: Resource<Scope = NamespaceResourceScope>
+            + Clone
+            + DeserializeOwned
+            + fmt::Debug
+            + Send
+            + 'static,
+        K::DynamicType: Default,
+    {
+        let (runtime, api) = self.namespaced::<K>()?;
+        log::debug!(
+            "Listing and watching {} matching {:?}",
+            K::plural(&K::DynamicType::default()),
+            &params
+        );
+        let list = runtime.block_on(api.list(&params))?;
+        let version = list.metadata.resource_version.unwrap_or_default();
+        let events = futures::stream::once(async move { api.watch(&params, &version).await })
+            .try_flatten()
+            .map_err(anyhow::Error::from);
+        Ok((list.items, events.boxed()))
+    }
+
+    /// Runs a future on the client's runtime, such as one consuming the
+    /// streams from [`KubeClient::list_and_watch`].
+    pub fn block_on<F: Future>(&self, future: F) -> anyhow::Result<F::Output> {
+        Ok(self.connect()?.runtime.block_on(future))
+    }
+
+    fn namespaced<K>(&self) -> anyhow::Result<(&Runtime, Api<K>)>
+    where
+        K: Resource<Scope = NamespaceResourceScope>,
+        K::DynamicType: Default,
+    {
+        let connection = self.connect()?;
+        Ok((
+            &connection.runtime,
+            Api::default_namespaced(connection.client.clone()),
+        ))
+    }
+
+    fn connect(&self) -> anyhow::Result<&Connection> {
+        if let Some(connection) = self.connection.get() {
+            return Ok(connection);
+        }
+
+        log::debug!("Connecting to Kubernetes using context {}", &self.context);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let options = KubeConfigOptions {
+            context: Some(self.context.clone()),
+            cluster: None,
+            user: None,
+        };
+        let client = runtime.block_on(async {
+            let config = kube::Config::from_kubeconfig(&options).await?;
+            anyhow::Ok(kube::Client::try_from(config)?)
+        })?;
+        Ok(self
+            .connection
+            .get_or_init(|| Connection { client, runtime }))
+    }
+}
+
+/// Flags for kubectl commands which connect the terminal to a container,
+/// only allocating a TTY when there's one to connect.
+fn terminal_flags(tty: bool) -> &'static [&'static str] {
+    if tty {
+        &["--stdin", "--tty"]
+    } else {
+        &["--stdin"]
     }
 }
 
@@ -123,6 +733,12 @@ impl Selector {
         }
     }
 
+    pub fn matches(&self, labels: Option<&BTreeMap<String, String>>) -> bool {
+        self.labels
+            .iter()
+            .all(|(key, value)| labels.and_then(|labels| labels.get(key)) == Some(value))
+    }
+
     pub fn to_string(&self) -> String {
         self.labels
             .iter()
@@ -1,9 +1,10 @@
 use super::kubectl;
+use super::runner::CommandRunner;
 use kube::config::{NamedAuthInfo, NamedCluster};
 use std::io::Write;
 use tempfile::NamedTempFile;
 
-pub fn write_auth(auth: NamedAuthInfo) -> anyhow::Result<()> {
+pub fn write_auth(runner: &dyn CommandRunner, auth: NamedAuthInfo) -> anyhow::Result<()> {
     let mut args = vec![
         String::from("config"),
         String::from("set-credentials"),
@@ -38,24 +39,33 @@ pub fn write_auth(auth: NamedAuthInfo) -> anyhow::Result<()> {
             }
         };
     };
-    kubectl::run_print(&args)
+    kubectl::run_print(runner, &args)
 }
 
-pub fn write_context(name: &str, auth: &str, cluster: &str, namespace: &str) -> anyhow::Result<()> {
-    kubectl::run_print(&[
-        "config",
-        "set-context",
-        name,
-        "--cluster",
-        cluster,
-        "--user",
-        auth,
-        "--namespace",
-        namespace,
-    ])
+pub fn write_context(
+    runner: &dyn CommandRunner,
+    name: &str,
+    auth: &str,
+    cluster: &str,
+    namespace: &str,
+) -> anyhow::Result<()> {
+    kubectl::run_print(
+        runner,
+        &[
+            "config",
+            "set-context",
+            name,
+            "--cluster",
+            cluster,
+            "--user",
+            auth,
+            "--namespace",
+            namespace,
+        ],
+    )
 }
 
-pub fn write_cluster(definition: NamedCluster) -> anyhow::Result<()> {
+pub fn write_cluster(runner: &dyn CommandRunner, definition: NamedCluster) -> anyhow::Result<()> {
     let cluster = &definition.cluster.ok_or(anyhow::Error::msg(format!(
         "Missing cluster definition for {}",
         &definition.name
@@ -86,7 +96,7 @@ pub fn write_cluster(definition: NamedCluster) -> anyhow::Result<()> {
         args.push(ca_path_name.to_string());
     }
 
-    kubectl::run_print(&args)?;
+    kubectl::run_print(runner, &args)?;
     ca_path.close()?;
     Ok(())
 }
@@ -1,10 +1,16 @@
+use super::runner::{CommandRunner, ExitStatus, Output, Process};
 use log;
-use std::ffi::OsStr;
-use std::fmt::Debug;
-use std::process::{Command, ExitStatus, Output};
+use std::io::{Read, Write};
+use tempfile::{NamedTempFile, TempPath};
 
-pub fn run_get_output(args: &[&str]) -> anyhow::Result<Output> {
-    let output = run(args).output()?;
+pub fn run_get_output<T: AsRef<str>>(
+    runner: &dyn CommandRunner,
+    args: &[T],
+) -> anyhow::Result<Output> {
+    let args = to_strs(args);
+    log::debug!("Running kubectl with {:?}", &args);
+    let output = runner.output("kubectl", &args)?;
+    log::debug!("kubectl exited with {}", output.status);
     match verify_exit(&args, output.status) {
         Ok(_) => Ok(output),
         Err(err) => {
@@ -13,24 +19,82 @@ pub fn run_get_output(args: &[&str]) -> anyhow::Result<Output> {
     }
 }
 
-pub fn run_print<T: AsRef<OsStr> + Clone + Debug>(args: &[T]) -> anyhow::Result<()> {
-    let mut child = run(args.as_ref()).spawn()?;
-    let status = child.wait()?;
-    verify_exit(args.as_ref(), status)
+/// Writes manifests, such as built kustomizations or generated pods, to a
+/// temporary file which kubectl can read.
+pub fn write_temp(manifests: &str) -> anyhow::Result<TempPath> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(manifests.as_bytes())?;
+    Ok(file.into_temp_path())
 }
 
-fn run<T: AsRef<OsStr> + Clone + Debug>(args: &[T]) -> Command {
-    log::debug!("Running kubectl with {:?}", &args.to_vec());
-    let mut command = Command::new("kubectl");
-    command.args(args);
-    command
+/// Runs kubectl with `input` streamed to its stdin, capturing its output.
+pub fn run_with_input<T: AsRef<str>>(
+    runner: &dyn CommandRunner,
+    args: &[T],
+    input: &mut (dyn Read + Send),
+) -> anyhow::Result<Output> {
+    let args = to_strs(args);
+    log::debug!("Running kubectl with {:?}", &args);
+    let output = runner.output_with_input("kubectl", &args, input)?;
+    log::debug!("kubectl exited with {}", output.status);
+    match verify_exit(&args, output.status) {
+        Ok(_) => Ok(output),
+        Err(err) => {
+            Err(err.context(String::from_utf8(output.stderr).unwrap_or("(binary)".to_string())))
+        }
+    }
 }
 
-fn verify_exit<T: AsRef<OsStr> + Clone + Debug>(
+/// Runs kubectl with its stdout streamed to `output`, for large downloads.
+pub fn run_to<T: AsRef<str>>(
+    runner: &dyn CommandRunner,
     args: &[T],
-    status: ExitStatus,
+    output: &mut (dyn Write + Send),
 ) -> anyhow::Result<()> {
+    let args = to_strs(args);
+    log::debug!("Running kubectl with {:?}", &args);
+    let result = runner.output_to("kubectl", &args, output)?;
+    log::debug!("kubectl exited with {}", result.status);
+    verify_exit(&args, result.status)
+        .map_err(|err| err.context(String::from_utf8_lossy(&result.stderr).into_owned()))
+}
+
+pub fn run_print<T: AsRef<str>>(runner: &dyn CommandRunner, args: &[T]) -> anyhow::Result<()> {
+    let args = to_strs(args);
+    let status = run_status(runner, &args)?;
+    verify_exit(&args, status)
+}
+
+/// Runs kubectl attached to the terminal without treating a non-zero exit as
+/// an error, for commands like `diff` which report results through their
+/// exit code.
+pub fn run_status<T: AsRef<str>>(
+    runner: &dyn CommandRunner,
+    args: &[T],
+) -> anyhow::Result<ExitStatus> {
+    let args = to_strs(args);
+    log::debug!("Running kubectl with {:?}", &args);
+    let status = runner.status("kubectl", &args)?;
     log::debug!("kubectl exited with {}", status);
+    Ok(status)
+}
+
+/// Starts kubectl in the background, for long-running commands like
+/// `port-forward`.
+pub fn spawn<T: AsRef<str>>(
+    runner: &dyn CommandRunner,
+    args: &[T],
+) -> anyhow::Result<Box<dyn Process>> {
+    let args = to_strs(args);
+    log::debug!("Starting kubectl with {:?}", &args);
+    runner.spawn("kubectl", &args)
+}
+
+fn to_strs<T: AsRef<str>>(args: &[T]) -> Vec<&str> {
+    args.iter().map(|arg| arg.as_ref()).collect()
+}
+
+pub fn verify_exit(args: &[&str], status: ExitStatus) -> anyhow::Result<()> {
     if status.success() {
         Ok(())
     } else {
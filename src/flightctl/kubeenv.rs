@@ -1,17 +1,36 @@
 use super::kubeclient::KubeClient;
 use k8s_openapi::api::core::v1 as k8s;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::rc::Rc;
 
+#[derive(Serialize)]
 pub struct ResolvedVar {
     pub name: Rc<String>,
+
+    #[serde(flatten)]
     pub value: ResolvedValue,
+
+    /// Set when a later definition of the same variable replaces this one,
+    /// so the kubelet never injects this value.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub overridden: bool,
 }
 
+/// Where a variable's value comes from. Secret values are never included.
+#[derive(Serialize)]
+#[serde(tag = "source", rename_all = "kebab-case")]
 pub enum ResolvedValue {
     Pod {
-        value: Option<String>,
+        value: String,
+
+        /// The literal value before `$(VAR)` references were expanded, when
+        /// it contained any.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        template: Option<String>,
     },
+    #[serde(rename_all = "camelCase")]
     ConfigMapKeyRef {
         config_map: Rc<String>,
         key: Rc<String>,
@@ -23,7 +42,27 @@ pub enum ResolvedValue {
     },
     FieldRef {
         path: String,
+        value: Option<String>,
     },
+    ResourceFieldRef {
+        resource: String,
+        value: Option<String>,
+    },
+}
+
+impl ResolvedValue {
+    /// The value this variable contributes when expanding `$(VAR)`
+    /// references. Secrets are never expanded, so their values are only
+    /// fetched when explicitly requested.
+    pub fn expansion(&self) -> Option<&str> {
+        match self {
+            ResolvedValue::Pod { value, .. } => Some(value),
+            ResolvedValue::ConfigMapKeyRef { value, .. } => value.as_deref().map(|v| v.as_str()),
+            ResolvedValue::SecretKeyRef { .. } => None,
+            ResolvedValue::FieldRef { value, .. } => value.as_deref(),
+            ResolvedValue::ResourceFieldRef { value, .. } => value.as_deref(),
+        }
+    }
 }
 
 pub struct Resolver<'c> {
@@ -31,99 +70,366 @@ pub struct Resolver<'c> {
 }
 
 impl<'c> Resolver<'c> {
-    pub fn new(client: &'c KubeClient) -> Resolver<'c> {
+    pub fn new(client: &'c KubeClient<'c>) -> Resolver<'c> {
         Resolver {
             cache: Cache::new(client),
         }
     }
 
-    pub fn resolve(&mut self, container: k8s::Container) -> Vec<ResolvedVar> {
+    /// Resolves a container's environment the way the kubelet builds it:
+    /// `envFrom` sources are imported in order with their prefixes, then
+    /// `env` entries are applied in order, expanding `$(VAR)` references to
+    /// variables defined before them. Variables replaced by a later
+    /// definition are kept but marked as overridden.
+    pub fn resolve(&mut self, pod: &k8s::Pod, container: k8s::Container) -> Vec<ResolvedVar> {
         let mut env: Vec<ResolvedVar> = Vec::new();
-        let container_envs = container.env.unwrap_or(vec![]);
-        env.append(&mut self.container_env_values(container_envs));
-        let container_env_sources = container.env_from.unwrap_or(vec![]);
-        env.append(&mut self.container_env_from_values(container_env_sources));
+
+        for env_source in container.env_from.clone().unwrap_or_default() {
+            env.append(&mut self.container_env_from_values(env_source));
+        }
+
+        for env_var in container.env.clone().unwrap_or_default() {
+            if let Some(var) = self.container_env_value(pod, &container, &env, env_var) {
+                env.push(var);
+            }
+        }
+
+        for index in 0..env.len() {
+            let name = &env[index].name;
+            env[index].overridden = env[index + 1..].iter().any(|later| &later.name == name);
+        }
         env.sort_by(|a, b| a.name.cmp(&b.name));
         env
     }
 
-    fn container_env_values(&mut self, container_envs: Vec<k8s::EnvVar>) -> Vec<ResolvedVar> {
-        let mut vars = Vec::new();
-        for env_var in container_envs {
-            let name_ref = Rc::new(env_var.name);
-            vars.push(match (env_var.value, env_var.value_from) {
-                (None, Some(value_from)) => self.env_from_value(&name_ref, value_from),
-                (Some(value), _) => ResolvedVar {
-                    name: name_ref,
-                    value: ResolvedValue::Pod { value: Some(value) },
-                },
-                (None, None) => ResolvedVar {
-                    name: name_ref,
-                    value: ResolvedValue::Pod { value: None },
-                },
-            })
-        }
-        vars
+    /// Fetches the value of a secret key. Secret values are only fetched when
+    /// explicitly requested.
+    pub fn secret_value(&mut self, secret: &str, key: &str) -> Option<Rc<String>> {
+        let name_ref = Rc::new(String::from(secret));
+        self.cache.fetch_secret(&name_ref);
+        let secret = self.cache.secrets.get(&name_ref)?;
+        let value = secret.data.get(&String::from(key))?;
+        Some(Rc::clone(value))
     }
 
-    fn env_from_value(&mut self, name: &Rc<String>, value_from: k8s::EnvVarSource) -> ResolvedVar {
+    fn container_env_value(
+        &mut self,
+        pod: &k8s::Pod,
+        container: &k8s::Container,
+        defined: &[ResolvedVar],
+        env_var: k8s::EnvVar,
+    ) -> Option<ResolvedVar> {
+        let name_ref = Rc::new(env_var.name);
+        let value = match (env_var.value, env_var.value_from) {
+            (None, Some(value_from)) => self.env_from_value(pod, container, value_from)?,
+            (value, _) => {
+                let template = value.unwrap_or_default();
+                let value = expand(&template, |name| {
+                    defined
+                        .iter()
+                        .rev()
+                        .find(|var| var.name.as_str() == name)
+                        .and_then(|var| var.value.expansion())
+                        .map(String::from)
+                });
+                ResolvedValue::Pod {
+                    template: Some(template).filter(|template| template != &value),
+                    value,
+                }
+            }
+        };
+        Some(ResolvedVar {
+            name: name_ref,
+            value,
+            overridden: false,
+        })
+    }
+
+    fn env_from_value(
+        &mut self,
+        pod: &k8s::Pod,
+        container: &k8s::Container,
+        value_from: k8s::EnvVarSource,
+    ) -> Option<ResolvedValue> {
         let value = match (
             value_from.config_map_key_ref,
             value_from.field_ref,
             value_from.resource_field_ref,
             value_from.secret_key_ref,
         ) {
-            (Some(config_map_key_ref), _, _, _) => match config_map_key_ref.name {
-                Some(config_map_name) => {
-                    let config_map_ref = Rc::new(config_map_name);
-                    self.cache
-                        .reference_config_map_key(&config_map_ref, &config_map_key_ref.key)
+            (Some(config_map_key_ref), _, _, _) => {
+                let config_map_ref =
+                    Rc::new(config_map_key_ref.name.unwrap_or(String::from("null")));
+                let value = self
+                    .cache
+                    .reference_config_map_key(&config_map_ref, &config_map_key_ref.key);
+                match value {
+                    ResolvedValue::ConfigMapKeyRef { value: None, .. }
+                        if config_map_key_ref.optional == Some(true) =>
+                    {
+                        return None
+                    }
+                    value => value,
                 }
-                None => ResolvedValue::ConfigMapKeyRef {
-                    config_map: Rc::new(String::from("null")),
-                    key: Rc::new(config_map_key_ref.key),
-                    value: None,
-                },
-            },
+            }
             (_, Some(field_ref), _, _) => ResolvedValue::FieldRef {
+                value: pod_field(pod, &field_ref.field_path),
                 path: field_ref.field_path,
             },
-            (_, _, _, _) => ResolvedValue::Pod { value: None },
+            (_, _, Some(resource_field_ref), _) => {
+                let resources = match &resource_field_ref.container_name {
+                    Some(name) if name != &container.name => pod
+                        .spec
+                        .as_ref()
+                        .and_then(|spec| spec.containers.iter().find(|c| &c.name == name))
+                        .and_then(|c| c.resources.as_ref()),
+                    _ => container.resources.as_ref(),
+                };
+                ResolvedValue::ResourceFieldRef {
+                    value: resource_value(
+                        resources,
+                        &resource_field_ref.resource,
+                        resource_field_ref.divisor.as_ref(),
+                    ),
+                    resource: resource_field_ref.resource,
+                }
+            }
+            (_, _, _, Some(secret_key_ref)) => {
+                let secret_ref = Rc::new(secret_key_ref.name.unwrap_or(String::from("null")));
+                if secret_key_ref.optional == Some(true)
+                    && !self.cache.has_secret_key(&secret_ref, &secret_key_ref.key)
+                {
+                    return None;
+                }
+                ResolvedValue::SecretKeyRef {
+                    secret: secret_ref,
+                    key: Rc::new(secret_key_ref.key),
+                }
+            }
+            (None, None, None, None) => ResolvedValue::Pod {
+                value: String::new(),
+                template: None,
+            },
         };
-        ResolvedVar {
-            name: Rc::clone(name),
-            value: value,
-        }
+        Some(value)
     }
 
-    fn container_env_from_values(
-        &mut self,
-        env_sources: Vec<k8s::EnvFromSource>,
-    ) -> Vec<ResolvedVar> {
-        let mut vars = Vec::new();
-        for env_source in env_sources {
+    fn container_env_from_values(&mut self, env_source: k8s::EnvFromSource) -> Vec<ResolvedVar> {
+        let prefix = env_source.prefix.unwrap_or_default();
+        let (kind, name, optional, imported) =
             match (env_source.config_map_ref, env_source.secret_ref) {
-                (
-                    Some(k8s::ConfigMapEnvSource {
-                        name: Some(name), ..
-                    }),
-                    _,
-                ) => {
-                    vars.append(&mut self.cache.import_config_map(&name));
+                (Some(k8s::ConfigMapEnvSource { name, optional }), _) => {
+                    let name = name.unwrap_or_default();
+                    let imported = self.cache.import_config_map(&name, &prefix);
+                    ("configmap", name, optional, imported)
+                }
+                (None, Some(k8s::SecretEnvSource { name, optional })) => {
+                    let name = name.unwrap_or_default();
+                    let imported = self.cache.import_secret(&name, &prefix);
+                    ("secret", name, optional, imported)
                 }
-                (
-                    None,
-                    Some(k8s::SecretEnvSource {
-                        name: Some(name), ..
-                    }),
-                ) => {
-                    vars.append(&mut self.cache.import_secret(&name));
+                (None, None) => return Vec::new(),
+            };
+
+        match imported {
+            Some(vars) => vars
+                .into_iter()
+                .filter(|var| {
+                    let valid = is_env_var_name(&var.name);
+                    if !valid {
+                        log::debug!(
+                            "Skipping invalid variable name {} from {}/{}",
+                            var.name,
+                            kind,
+                            name
+                        );
+                    }
+                    valid
+                })
+                .collect(),
+            None => {
+                if optional != Some(true) {
+                    log::warn!(
+                        "Couldn't find {}/{}, which the container requires",
+                        kind,
+                        name
+                    );
+                }
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// Expands `$(VAR)` references the way Kubernetes does for container
+/// environment variables and commands.
+///
+/// `$$` escapes a literal `$`, and references to variables which `lookup`
+/// can't resolve are left as written.
+pub fn expand<F>(template: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(remaining) = after.strip_prefix('$') {
+            result.push('$');
+            rest = remaining;
+        } else if let Some(reference) = after.strip_prefix('(') {
+            match reference.find(')') {
+                Some(end) => {
+                    let name = &reference[..end];
+                    match lookup(name) {
+                        Some(value) => result.push_str(&value),
+                        None => {
+                            result.push_str("$(");
+                            result.push_str(name);
+                            result.push(')');
+                        }
+                    }
+                    rest = &reference[end + 1..];
+                }
+                None => {
+                    result.push_str("$(");
+                    rest = reference;
                 }
-                _ => (),
             }
+        } else {
+            result.push('$');
+            rest = after;
         }
-        vars
     }
+
+    result.push_str(rest);
+    result
+}
+
+/// Computes the value the kubelet injects for a `resourceFieldRef`, such as
+/// `limits.memory`, rounding up to a whole multiple of the divisor.
+///
+/// Returns `None` for unset limits, which the kubelet fills in from the
+/// node's allocatable resources.
+pub fn resource_value(
+    resources: Option<&k8s::ResourceRequirements>,
+    resource: &str,
+    divisor: Option<&Quantity>,
+) -> Option<String> {
+    let (kind, name) = resource.split_once('.')?;
+    let quantities = match kind {
+        "limits" => resources.and_then(|resources| resources.limits.as_ref()),
+        "requests" => resources.and_then(|resources| resources.requests.as_ref()),
+        _ => return None,
+    };
+    let amount = match quantities.and_then(|quantities| quantities.get(name)) {
+        Some(Quantity(quantity)) => parse_quantity(quantity)?,
+        None if kind == "requests" => 0,
+        None => return None,
+    };
+    let divisor = match divisor {
+        Some(Quantity(divisor)) => parse_quantity(divisor)?,
+        None => NANOS,
+    };
+
+    if divisor <= 0 {
+        return None;
+    }
+    Some(((amount + divisor - 1) / divisor).to_string())
+}
+
+const NANOS: i128 = 1_000_000_000;
+
+/// Parses a Kubernetes quantity like `500m` or `2Gi` into billionths.
+fn parse_quantity(quantity: &str) -> Option<i128> {
+    let number_end = quantity
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '+' || c == '-'))
+        .unwrap_or(quantity.len());
+    let (number, suffix) = quantity.split_at(number_end);
+
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let digits: i128 = format!("{}{}", whole, fraction).parse().ok()?;
+    let scale = 10i128.checked_pow(fraction.len() as u32)?;
+
+    let multiplier: i128 = match suffix {
+        "n" => 1,
+        "u" => 1_000,
+        "m" => 1_000_000,
+        "" => NANOS,
+        "k" => NANOS * 1_000,
+        "M" => NANOS * 1_000_000,
+        "G" => NANOS * 1_000_000_000,
+        "T" => NANOS * 1_000_000_000_000,
+        "P" => NANOS * 1_000_000_000_000_000,
+        "E" => NANOS * 1_000_000_000_000_000_000,
+        "Ki" => NANOS << 10,
+        "Mi" => NANOS << 20,
+        "Gi" => NANOS << 30,
+        "Ti" => NANOS << 40,
+        "Pi" => NANOS << 50,
+        "Ei" => NANOS << 60,
+        _ => {
+            let exponent: i32 = suffix
+                .strip_prefix('e')
+                .or_else(|| suffix.strip_prefix('E'))?
+                .parse()
+                .ok()?;
+            if exponent >= 0 {
+                NANOS.checked_mul(10i128.checked_pow(exponent as u32)?)?
+            } else {
+                NANOS / 10i128.checked_pow(exponent.unsigned_abs())?
+            }
+        }
+    };
+
+    Some(digits.checked_mul(multiplier)? / scale)
+}
+
+fn pod_field(pod: &k8s::Pod, path: &str) -> Option<String> {
+    let metadata = &pod.metadata;
+    match path {
+        "metadata.name" => metadata.name.clone(),
+        "metadata.namespace" => metadata.namespace.clone(),
+        "metadata.uid" => metadata.uid.clone(),
+        "spec.nodeName" => pod.spec.as_ref()?.node_name.clone(),
+        "spec.serviceAccountName" => pod.spec.as_ref()?.service_account_name.clone(),
+        "status.hostIP" => pod.status.as_ref()?.host_ip.clone(),
+        "status.podIP" => pod.status.as_ref()?.pod_ip.clone(),
+        "status.podIPs" => Some(
+            pod.status
+                .as_ref()?
+                .pod_ips
+                .as_ref()?
+                .iter()
+                .filter_map(|pod_ip| pod_ip.ip.clone())
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+        _ => {
+            let (map, key) = if let Some(key) = path.strip_prefix("metadata.labels") {
+                (metadata.labels.as_ref()?, key)
+            } else if let Some(key) = path.strip_prefix("metadata.annotations") {
+                (metadata.annotations.as_ref()?, key)
+            } else {
+                return None;
+            };
+            let key = key.strip_prefix("['")?.strip_suffix("']")?;
+            map.get(key).cloned()
+        }
+    }
+}
+
+/// Checks a name the way the API server validates environment variable
+/// names: letters, digits, `_`, `-` or `.`, not starting with a digit.
+fn is_env_var_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
 }
 
 struct SharedMap {
@@ -168,13 +474,13 @@ impl SharedMap {
 }
 
 struct Cache<'c> {
-    client: &'c KubeClient,
+    client: &'c KubeClient<'c>,
     config_maps: HashMap<Rc<String>, SharedMap>,
     secrets: HashMap<Rc<String>, SharedMap>,
 }
 
 impl<'c> Cache<'c> {
-    fn new(client: &'c KubeClient) -> Cache<'c> {
+    fn new(client: &'c KubeClient<'c>) -> Cache<'c> {
         Cache {
             client: client,
             config_maps: HashMap::new(),
@@ -200,71 +506,69 @@ impl<'c> Cache<'c> {
         Some(Rc::clone(value))
     }
 
-    fn import_config_map(&mut self, name: &str) -> Vec<ResolvedVar> {
+    fn import_config_map(&mut self, name: &str, prefix: &str) -> Option<Vec<ResolvedVar>> {
         let name_ref = Rc::new(String::from(name));
         self.fetch_config_map(&name_ref);
-        match self.config_maps.get(&name_ref) {
-            Some(config_map) => config_map
+        let config_map = self.config_maps.get(&name_ref)?;
+        Some(
+            config_map
                 .data
                 .iter()
                 .map(|(key, value)| ResolvedVar {
-                    name: Rc::clone(key),
+                    name: Rc::new(format!("{}{}", prefix, key)),
                     value: ResolvedValue::ConfigMapKeyRef {
                         config_map: Rc::clone(&name_ref),
                         key: Rc::clone(key),
                         value: Some(Rc::clone(value)),
                     },
+                    overridden: false,
                 })
                 .collect(),
-            None => Vec::new(),
-        }
+        )
     }
 
     fn fetch_config_map(&mut self, name: &Rc<String>) {
         if !self.config_maps.contains_key(name) {
-            match self
-                .client
-                .fetch_resource::<k8s::ConfigMap>(&format!("configmap/{}", &name))
-            {
-                Ok(config_map) => {
-                    self.config_maps
-                        .insert(Rc::clone(name), SharedMap::from_config_map(config_map));
-                }
-                Err(_) => (),
+            if let Ok(config_map) = self.client.fetch_resource::<k8s::ConfigMap>(name) {
+                self.config_maps
+                    .insert(Rc::clone(name), SharedMap::from_config_map(config_map));
             }
         }
     }
 
-    fn import_secret(&mut self, name: &str) -> Vec<ResolvedVar> {
+    fn import_secret(&mut self, name: &str, prefix: &str) -> Option<Vec<ResolvedVar>> {
         let name_ref = Rc::new(String::from(name));
         self.fetch_secret(&name_ref);
-        match self.secrets.get(&name_ref) {
-            Some(secret) => secret
+        let secret = self.secrets.get(&name_ref)?;
+        Some(
+            secret
                 .data
-                .iter()
-                .map(|(key, _)| ResolvedVar {
-                    name: Rc::clone(key),
+                .keys()
+                .map(|key| ResolvedVar {
+                    name: Rc::new(format!("{}{}", prefix, key)),
                     value: ResolvedValue::SecretKeyRef {
                         secret: Rc::clone(&name_ref),
                         key: Rc::clone(key),
                     },
+                    overridden: false,
                 })
                 .collect(),
-            None => Vec::new(),
-        }
+        )
+    }
+
+    fn has_secret_key(&mut self, name: &Rc<String>, key: &str) -> bool {
+        self.fetch_secret(name);
+        self.secrets
+            .get(name)
+            .map(|secret| secret.data.contains_key(&String::from(key)))
+            .unwrap_or(false)
     }
 
     fn fetch_secret(&mut self, name: &Rc<String>) {
         if !self.secrets.contains_key(name) {
-            match self
-                .client
-                .fetch_resource::<k8s::Secret>(&format!("secret/{}", &name))
-            {
-                Ok(secret) => {
-                    self.secrets
-                        .insert(Rc::clone(name), SharedMap::from_secret(secret));
-                }
-                Err(_) => (),
+            if let Ok(secret) = self.client.fetch_resource::<k8s::Secret>(name) {
+                self.secrets
+                    .insert(Rc::clone(name), SharedMap::from_secret(secret));
             }
         }
     }
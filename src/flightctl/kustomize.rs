@@ -0,0 +1,77 @@
+use super::config::{ApplicationManifests, ManifestConfig};
+use super::kubectl;
+use super::runner::CommandRunner;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+
+const ROLLOUT_KINDS: [&str; 3] = ["DaemonSet", "Deployment", "StatefulSet"];
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    kind: String,
+    metadata: ObjectMeta,
+}
+
+/// A resource which reports rollout status, such as `deployment/web`.
+#[derive(Debug, PartialEq)]
+pub struct RolloutResource {
+    pub name: String,
+    pub namespace: Option<String>,
+}
+
+impl fmt::Display for RolloutResource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Returns the kustomization target for a release's manifests.
+///
+/// Releases point at a path within the application's manifests repository.
+/// Local repositories, which are resolved against the configuration file's
+/// directory when it's loaded, are joined with the path directly, while
+/// anything else is treated as a remote repository using kustomize's
+/// `repo//path` syntax.
+pub fn target(manifests: &ApplicationManifests, config: &ManifestConfig) -> String {
+    match config {
+        ManifestConfig::Kustomize { path } => {
+            let repo = Path::new(&manifests.repo);
+            if manifests.repo.is_empty() || manifests.repo == "." {
+                path.clone()
+            } else if repo.is_dir() {
+                repo.join(path).to_string_lossy().into_owned()
+            } else {
+                format!("{}//{}", manifests.repo.trim_end_matches('/'), path)
+            }
+        }
+    }
+}
+
+pub fn build(runner: &dyn CommandRunner, target: &str) -> anyhow::Result<String> {
+    log::info!("Building manifests from {}", target);
+    let output = kubectl::run_get_output(runner, &["kustomize", target])?;
+    let manifests = String::from_utf8(output.stdout)?;
+    Ok(manifests)
+}
+
+/// Lists resources in built manifests which report rollout status, along
+/// with any namespace their manifests set.
+pub fn rollout_resources(manifests: &str) -> anyhow::Result<Vec<RolloutResource>> {
+    let mut resources = Vec::new();
+
+    for document in serde_yaml::Deserializer::from_str(manifests) {
+        let manifest: Option<Manifest> = Option::deserialize(document)?;
+        if let Some(Manifest { kind, metadata }) = manifest {
+            if let (true, Some(name)) = (ROLLOUT_KINDS.contains(&kind.as_str()), metadata.name) {
+                resources.push(RolloutResource {
+                    name: format!("{}/{}", kind.to_lowercase(), name),
+                    namespace: metadata.namespace,
+                });
+            }
+        }
+    }
+
+    Ok(resources)
+}
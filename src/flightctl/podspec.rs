@@ -0,0 +1,215 @@
+use k8s_openapi::api::apps::v1 as apps;
+use k8s_openapi::api::batch::v1 as batch;
+use k8s_openapi::api::core::v1 as k8s;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use std::collections::BTreeMap;
+
+pub const MANAGED_BY_LABEL: &str = "app.kubernetes.io/managed-by";
+pub const MANAGED_BY: &str = "flightctl";
+
+pub const RELAY_CONTAINER: &str = "relay";
+
+/// How long finished jobs are kept for inspection before Kubernetes deletes
+/// them.
+const JOB_TTL_SECONDS: i32 = 86400;
+
+/// Builds a pod from a deployment's pod template, in the deployment's
+/// namespace, returning it with the deployment's name. The pod has no name
+/// or status.
+pub fn template_pod(deployment: apps::Deployment) -> (String, k8s::Pod) {
+    let name = deployment.metadata.name.unwrap_or_default();
+    let template = deployment
+        .spec
+        .map(|spec| spec.template)
+        .unwrap_or_default();
+    let mut metadata = template.metadata.unwrap_or_default();
+    metadata.namespace = deployment.metadata.namespace;
+    let pod = k8s::Pod {
+        metadata,
+        spec: template.spec,
+        status: None,
+    };
+    (name, pod)
+}
+
+/// Builds a standalone console pod from a deployment's pod template.
+///
+/// The pod keeps the template's containers, environment, volumes and service
+/// account, but none of its labels, so that services and the deployment's
+/// ReplicaSet never select it. The console container runs `command` with
+/// stdin attached, and a TTY if `tty` is set, without probes and optionally
+/// with its own resources.
+pub fn console_pod(
+    template: k8s::Pod,
+    deployment: &str,
+    container: &str,
+    command: &[String],
+    resources: Option<&k8s::ResourceRequirements>,
+    tty: bool,
+) -> anyhow::Result<k8s::Pod> {
+    command_pod(
+        template,
+        deployment,
+        "console",
+        container,
+        command,
+        resources,
+        Some(tty),
+    )
+}
+
+/// Builds a Job which runs `command` once in a pod cloned from a
+/// deployment's pod template, the same way as [`console_pod`] but without a
+/// terminal. The job isn't retried and is stopped after `deadline_seconds`.
+pub fn job(
+    template: k8s::Pod,
+    deployment: &str,
+    container: &str,
+    command: &[String],
+    deadline_seconds: i64,
+) -> anyhow::Result<batch::Job> {
+    let pod = command_pod(template, deployment, "run", container, command, None, None)?;
+
+    Ok(batch::Job {
+        metadata: ObjectMeta {
+            generate_name: pod.metadata.generate_name,
+            namespace: pod.metadata.namespace,
+            labels: pod.metadata.labels.clone(),
+            ..ObjectMeta::default()
+        },
+        spec: Some(batch::JobSpec {
+            active_deadline_seconds: Some(deadline_seconds),
+            backoff_limit: Some(0),
+            ttl_seconds_after_finished: Some(JOB_TTL_SECONDS),
+            template: k8s::PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    annotations: pod.metadata.annotations,
+                    labels: pod.metadata.labels,
+                    ..ObjectMeta::default()
+                }),
+                spec: pod.spec,
+            },
+            ..batch::JobSpec::default()
+        }),
+        status: None,
+    })
+}
+
+/// Builds a pod which relays TCP connections on `port` to `host`, for
+/// reaching databases which are only accessible from inside the cluster.
+/// The relay stops after `deadline_seconds` in case it's never deleted.
+pub fn relay_pod(
+    name: &str,
+    image: &str,
+    host: &str,
+    port: u16,
+    deadline_seconds: i64,
+) -> k8s::Pod {
+    k8s::Pod {
+        metadata: ObjectMeta {
+            generate_name: Some(format!("{}-relay-", name)),
+            labels: Some(BTreeMap::from([(
+                String::from(MANAGED_BY_LABEL),
+                String::from(MANAGED_BY),
+            )])),
+            ..ObjectMeta::default()
+        },
+        spec: Some(k8s::PodSpec {
+            active_deadline_seconds: Some(deadline_seconds),
+            automount_service_account_token: Some(false),
+            containers: vec![k8s::Container {
+                name: String::from(RELAY_CONTAINER),
+                image: Some(String::from(image)),
+                command: Some(vec![
+                    String::from("socat"),
+                    format!("TCP-LISTEN:{},fork,reuseaddr", port),
+                    format!("TCP:{}:{}", host, port),
+                ]),
+                ports: Some(vec![k8s::ContainerPort {
+                    container_port: i32::from(port),
+                    ..k8s::ContainerPort::default()
+                }]),
+                ..k8s::Container::default()
+            }],
+            restart_policy: Some(String::from("Never")),
+            ..k8s::PodSpec::default()
+        }),
+        status: None,
+    }
+}
+
+/// Whether a container has started and is still running.
+pub fn is_running(pod: &k8s::Pod, container: &str) -> bool {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.container_statuses.as_ref())
+        .and_then(|statuses| statuses.iter().find(|status| status.name == container))
+        .and_then(|status| status.state.as_ref())
+        .is_some_and(|state| state.running.is_some())
+}
+
+/// Returns the exit code of a container which has terminated.
+pub fn exit_code(pod: &k8s::Pod, container: &str) -> Option<i32> {
+    pod.status
+        .as_ref()?
+        .container_statuses
+        .as_ref()?
+        .iter()
+        .find(|status| status.name == container)?
+        .state
+        .as_ref()?
+        .terminated
+        .as_ref()
+        .map(|terminated| terminated.exit_code)
+}
+
+/// Builds a pod which runs `command` in `container`. Interactive pods attach
+/// stdin, with a TTY if `tty` is `Some(true)`, while `None` runs detached.
+fn command_pod(
+    template: k8s::Pod,
+    deployment: &str,
+    purpose: &str,
+    container: &str,
+    command: &[String],
+    resources: Option<&k8s::ResourceRequirements>,
+    tty: Option<bool>,
+) -> anyhow::Result<k8s::Pod> {
+    let mut spec = template.spec.unwrap_or_default();
+    let target = spec
+        .containers
+        .iter_mut()
+        .find(|c| c.name == container)
+        .ok_or(anyhow::anyhow!(
+            "Couldn't find container {} in deployment/{}",
+            container,
+            deployment
+        ))?;
+
+    target.command = Some(command.to_vec());
+    target.args = None;
+    target.stdin = Some(tty.is_some());
+    target.stdin_once = Some(tty.is_some());
+    target.tty = Some(tty == Some(true));
+    target.liveness_probe = None;
+    target.readiness_probe = None;
+    target.startup_probe = None;
+    if let Some(resources) = resources {
+        target.resources = Some(resources.clone());
+    }
+    spec.restart_policy = Some(String::from("Never"));
+
+    Ok(k8s::Pod {
+        metadata: ObjectMeta {
+            generate_name: Some(format!("{}-{}-", deployment, purpose)),
+            namespace: template.metadata.namespace,
+            annotations: template.metadata.annotations,
+            labels: Some(BTreeMap::from([(
+                String::from(MANAGED_BY_LABEL),
+                String::from(MANAGED_BY),
+            )])),
+            ..ObjectMeta::default()
+        },
+        spec: Some(spec),
+        status: None,
+    })
+}
@@ -0,0 +1,18 @@
+use super::config::{Config, Release};
+use super::runner::CommandRunner;
+use super::selector::Selector;
+use super::{authorize, context};
+use log;
+
+pub fn run<'a>(
+    runner: &dyn CommandRunner,
+    config: &'a Config,
+    selector: Selector,
+) -> anyhow::Result<&'a Release> {
+    log::debug!("Beginning preflight");
+    let release = selector.apply(config)?;
+    authorize::run(runner, config, release)?;
+    context::prepare(runner, config, release)?;
+    log::debug!("Preflight complete");
+    Ok(release)
+}
@@ -0,0 +1,244 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+pub mod fake;
+
+/// Runs external programs such as the AWS CLI and kubectl.
+///
+/// Everything that shells out goes through this trait so that workflows like
+/// preflight can be exercised with a scripted runner instead of real clouds.
+pub trait CommandRunner: fmt::Debug {
+    /// Runs a program to completion, capturing stdout and stderr.
+    fn output(&self, program: &str, args: &[&str]) -> anyhow::Result<Output>;
+
+    /// Runs a program attached to the current terminal.
+    fn status(&self, program: &str, args: &[&str]) -> anyhow::Result<ExitStatus>;
+
+    /// Runs a program to completion, streaming `input` to its stdin and
+    /// capturing stdout and stderr.
+    fn output_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &mut (dyn Read + Send),
+    ) -> anyhow::Result<Output>;
+
+    /// Runs a program to completion, streaming its stdout to `output` and
+    /// capturing stderr. The returned output has no stdout.
+    fn output_to(
+        &self,
+        program: &str,
+        args: &[&str],
+        output: &mut (dyn Write + Send),
+    ) -> anyhow::Result<Output>;
+
+    /// Starts a program in the background, with its stderr attached to the
+    /// current terminal. On unix it runs in its own process group, so Ctrl-C
+    /// meant for a program in the foreground doesn't stop it.
+    fn spawn(&self, program: &str, args: &[&str]) -> anyhow::Result<Box<dyn Process>>;
+
+    /// Starts a program attached to the current terminal with extra
+    /// environment variables, such as credentials which shouldn't appear in
+    /// its arguments, returning while it runs.
+    fn spawn_with_env(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(&str, &str)],
+    ) -> anyhow::Result<Box<dyn Process>>;
+}
+
+/// A program running in the background.
+pub trait Process: fmt::Debug {
+    /// Returns the exit status if the program has exited, without waiting.
+    fn try_wait(&mut self) -> anyhow::Result<Option<ExitStatus>>;
+
+    /// Stops the program if it's still running.
+    fn kill(&mut self) -> anyhow::Result<()>;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Output {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExitStatus {
+    code: Option<i32>,
+}
+
+impl ExitStatus {
+    pub fn from_code(code: i32) -> ExitStatus {
+        ExitStatus { code: Some(code) }
+    }
+
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
+}
+
+impl From<std::process::ExitStatus> for ExitStatus {
+    fn from(status: std::process::ExitStatus) -> ExitStatus {
+        ExitStatus {
+            code: status.code(),
+        }
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "exit status: {}", code),
+            None => write!(f, "terminated by signal"),
+        }
+    }
+}
+
+/// Runs programs on the host system.
+#[derive(Debug, Default)]
+pub struct SystemRunner;
+
+impl Process for Child {
+    fn try_wait(&mut self) -> anyhow::Result<Option<ExitStatus>> {
+        Ok(Child::try_wait(self)?.map(ExitStatus::from))
+    }
+
+    fn kill(&mut self) -> anyhow::Result<()> {
+        if Child::try_wait(self)?.is_none() {
+            Child::kill(self)?;
+            self.wait()?;
+        }
+        Ok(())
+    }
+}
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, program: &str, args: &[&str]) -> anyhow::Result<Output> {
+        let output = Command::new(program).args(args).output()?;
+        Ok(Output {
+            status: output.status.into(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    fn status(&self, program: &str, args: &[&str]) -> anyhow::Result<ExitStatus> {
+        let status = Command::new(program).args(args).status()?;
+        Ok(status.into())
+    }
+
+    fn output_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &mut (dyn Read + Send),
+    ) -> anyhow::Result<Output> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or(anyhow::anyhow!("Couldn't open stdin for {}", program))?;
+
+        // Write from another thread so that a program which fills its output
+        // pipes before reading all of its input can't deadlock.
+        let (written, output) = thread::scope(|scope| {
+            let writer = scope.spawn(move || io::copy(input, &mut stdin).and(stdin.flush()));
+            let output = child.wait_with_output();
+            (writer.join(), output)
+        });
+        let output = output?;
+
+        // A program which exits early closes its stdin; its exit status
+        // explains the failure better than the broken pipe does.
+        if output.status.success() {
+            written.map_err(|_| anyhow::anyhow!("Writing input to {} panicked", program))??;
+        }
+        Ok(Output {
+            status: output.status.into(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    fn output_to(
+        &self,
+        program: &str,
+        args: &[&str],
+        output: &mut (dyn Write + Send),
+    ) -> anyhow::Result<Output> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or(anyhow::anyhow!("Couldn't open stdout for {}", program))?;
+        let mut stderr = child
+            .stderr
+            .take()
+            .ok_or(anyhow::anyhow!("Couldn't open stderr for {}", program))?;
+
+        let (copied, errors) = thread::scope(|scope| {
+            let reader = scope.spawn(move || {
+                let mut errors = Vec::new();
+                stderr.read_to_end(&mut errors).map(|_| errors)
+            });
+            // Close stdout as soon as copying stops, so that a program can't
+            // block writing output nobody reads.
+            let mut stdout = stdout;
+            let copied = io::copy(&mut stdout, output).and(output.flush());
+            drop(stdout);
+            (copied, reader.join())
+        });
+        let status = child.wait()?;
+        let errors =
+            errors.map_err(|_| anyhow::anyhow!("Reading errors from {} panicked", program))??;
+        copied?;
+        Ok(Output {
+            status: status.into(),
+            stdout: vec![],
+            stderr: errors,
+        })
+    }
+
+    fn spawn(&self, program: &str, args: &[&str]) -> anyhow::Result<Box<dyn Process>> {
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null());
+        #[cfg(unix)]
+        command.process_group(0);
+        Ok(Box::new(command.spawn()?))
+    }
+
+    fn spawn_with_env(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(&str, &str)],
+    ) -> anyhow::Result<Box<dyn Process>> {
+        let child = Command::new(program)
+            .args(args)
+            .envs(env.iter().copied())
+            .spawn()?;
+        Ok(Box::new(child))
+    }
+}
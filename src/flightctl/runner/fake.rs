@@ -0,0 +1,187 @@
+use super::{CommandRunner, ExitStatus, Output, Process};
+use std::cell::RefCell;
+use std::io::{Read, Write};
+
+/// A `CommandRunner` which replays scripted results instead of running programs.
+///
+/// Each scripted response is matched against the command line of an
+/// invocation, and is consumed the first time it matches. A script matches
+/// when its words are a prefix of the invoked command line, which allows
+/// tests to ignore generated arguments like temporary file paths. Invoking a
+/// command without a matching script is an error.
+#[derive(Debug, Default)]
+pub struct FakeRunner {
+    scripts: RefCell<Vec<Script>>,
+    calls: RefCell<Vec<String>>,
+    inputs: RefCell<Vec<Vec<u8>>>,
+    envs: RefCell<Vec<Vec<(String, String)>>>,
+}
+
+/// A spawned program which exits with its scripted status the first time
+/// it's checked, unless it was scripted with `spawn_running`.
+#[derive(Debug)]
+pub struct FakeProcess {
+    status: Option<ExitStatus>,
+}
+
+#[derive(Debug)]
+struct Script {
+    command: Vec<String>,
+    output: Output,
+}
+
+impl FakeRunner {
+    pub fn new() -> FakeRunner {
+        FakeRunner::default()
+    }
+
+    /// Scripts a successful run of `command` which prints `stdout`.
+    pub fn succeed(&self, command: &str, stdout: &str) -> &FakeRunner {
+        self.respond(
+            command,
+            Output {
+                status: ExitStatus::from_code(0),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: vec![],
+            },
+        )
+    }
+
+    /// Scripts a failed run of `command` which prints `stderr`.
+    pub fn fail(&self, command: &str, code: i32, stderr: &str) -> &FakeRunner {
+        self.respond(
+            command,
+            Output {
+                status: ExitStatus::from_code(code),
+                stdout: vec![],
+                stderr: stderr.as_bytes().to_vec(),
+            },
+        )
+    }
+
+    /// Scripts a spawn of `command` which keeps running until killed.
+    pub fn spawn_running(&self, command: &str) -> &FakeRunner {
+        self.respond(command, Output::default())
+    }
+
+    pub fn respond(&self, command: &str, output: Output) -> &FakeRunner {
+        self.scripts.borrow_mut().push(Script {
+            command: command.split_whitespace().map(String::from).collect(),
+            output,
+        });
+        self
+    }
+
+    /// Command lines invoked so far, in order.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.borrow().clone()
+    }
+
+    /// Input written to commands run with `output_with_input`, in order.
+    pub fn inputs(&self) -> Vec<Vec<u8>> {
+        self.inputs.borrow().clone()
+    }
+
+    /// Environment variables passed to commands started with
+    /// `spawn_with_env`, in order.
+    pub fn envs(&self) -> Vec<Vec<(String, String)>> {
+        self.envs.borrow().clone()
+    }
+
+    /// Scripted command lines which were never invoked.
+    pub fn pending(&self) -> Vec<String> {
+        self.scripts
+            .borrow()
+            .iter()
+            .map(|script| script.command.join(" "))
+            .collect()
+    }
+
+    fn invoke(&self, program: &str, args: &[&str]) -> anyhow::Result<Output> {
+        let invocation: Vec<&str> = [program].iter().chain(args).copied().collect();
+        let command_line = invocation.join(" ");
+        log::debug!("Faking command: {}", &command_line);
+        self.calls.borrow_mut().push(command_line.clone());
+
+        let mut scripts = self.scripts.borrow_mut();
+        let position = scripts
+            .iter()
+            .position(|script| {
+                script.command.len() <= invocation.len()
+                    && script
+                        .command
+                        .iter()
+                        .zip(&invocation)
+                        .all(|(expected, actual)| expected == actual)
+            })
+            .ok_or(anyhow::anyhow!("Unexpected command: {}", command_line))?;
+        Ok(scripts.remove(position).output)
+    }
+}
+
+impl CommandRunner for FakeRunner {
+    fn output(&self, program: &str, args: &[&str]) -> anyhow::Result<Output> {
+        self.invoke(program, args)
+    }
+
+    fn status(&self, program: &str, args: &[&str]) -> anyhow::Result<ExitStatus> {
+        let output = self.invoke(program, args)?;
+        Ok(output.status)
+    }
+
+    fn output_with_input(
+        &self,
+        program: &str,
+        args: &[&str],
+        input: &mut (dyn Read + Send),
+    ) -> anyhow::Result<Output> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        self.inputs.borrow_mut().push(bytes);
+        self.invoke(program, args)
+    }
+
+    fn output_to(
+        &self,
+        program: &str,
+        args: &[&str],
+        output: &mut (dyn Write + Send),
+    ) -> anyhow::Result<Output> {
+        let mut result = self.invoke(program, args)?;
+        output.write_all(&result.stdout)?;
+        result.stdout.clear();
+        Ok(result)
+    }
+
+    fn spawn(&self, program: &str, args: &[&str]) -> anyhow::Result<Box<dyn Process>> {
+        let output = self.invoke(program, args)?;
+        Ok(Box::new(FakeProcess {
+            status: output.status.code().map(|_| output.status),
+        }))
+    }
+
+    fn spawn_with_env(
+        &self,
+        program: &str,
+        args: &[&str],
+        env: &[(&str, &str)],
+    ) -> anyhow::Result<Box<dyn Process>> {
+        self.envs.borrow_mut().push(
+            env.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        );
+        self.spawn(program, args)
+    }
+}
+
+impl Process for FakeProcess {
+    fn try_wait(&mut self) -> anyhow::Result<Option<ExitStatus>> {
+        Ok(self.status)
+    }
+
+    fn kill(&mut self) -> anyhow::Result<()> {
+        self.status.get_or_insert(ExitStatus::default());
+        Ok(())
+    }
+}
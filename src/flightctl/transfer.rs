@@ -0,0 +1,189 @@
+use ring::digest;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How often progress is redrawn.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Wraps a reader or writer to report how many bytes have been copied.
+///
+/// Progress is redrawn on stderr when it's a terminal, and logged once
+/// copying finishes.
+pub struct Progress<T> {
+    inner: T,
+    label: String,
+    total: Option<u64>,
+    done: u64,
+    drawn: Option<Instant>,
+    interactive: bool,
+}
+
+impl<T> Progress<T> {
+    pub fn new(inner: T, label: &str, total: Option<u64>) -> Progress<T> {
+        Progress {
+            inner,
+            label: String::from(label),
+            total,
+            done: 0,
+            drawn: None,
+            interactive: io::stderr().is_terminal(),
+        }
+    }
+
+    /// Reports the final count and returns the wrapped reader or writer.
+    pub fn finish(self) -> T {
+        if self.interactive {
+            eprintln!("\r{}", self.describe());
+        }
+        log::info!("{}", self.describe());
+        self.inner
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.done += count as u64;
+        if self.interactive
+            && self
+                .drawn
+                .is_none_or(|drawn| drawn.elapsed() >= PROGRESS_INTERVAL)
+        {
+            eprint!("\r{}", self.describe());
+            self.drawn = Some(Instant::now());
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.total {
+            Some(total) if total > 0 => format!(
+                "{}: {} / {} ({}%)",
+                self.label,
+                format_bytes(self.done),
+                format_bytes(total),
+                self.done * 100 / total
+            ),
+            _ => format!("{}: {}", self.label, format_bytes(self.done)),
+        }
+    }
+}
+
+impl<T: Read> Read for Progress<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.advance(count);
+        Ok(count)
+    }
+}
+
+impl<T: Write> Write for Progress<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.inner.write(buf)?;
+        self.advance(count);
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Formats a size using binary units, such as `1.5 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Computes SHA-256 checksums for a file, keyed as `.`, or for each file in
+/// a directory, keyed by relative paths like `./exports/users.csv`. These
+/// match what `sha256sum` reports from within the directory.
+pub fn local_checksums(path: &Path) -> io::Result<BTreeMap<String, String>> {
+    let mut checksums = BTreeMap::new();
+    if path.is_dir() {
+        collect_checksums(path, ".", &mut checksums)?;
+    } else {
+        checksums.insert(String::from("."), file_checksum(path)?);
+    }
+    Ok(checksums)
+}
+
+/// Parses `sha256sum` output. Output for a single file is keyed as `.`.
+pub fn parse_checksums(output: &str, single_file: bool) -> BTreeMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (checksum, name) = line.split_once(char::is_whitespace)?;
+            let name = name.trim_start().trim_start_matches('*');
+            let name = if single_file { "." } else { name };
+            Some((String::from(name), String::from(checksum)))
+        })
+        .collect()
+}
+
+/// Checks that every copied file has the same checksum at its destination.
+pub fn verify(
+    expected: &BTreeMap<String, String>,
+    actual: &BTreeMap<String, String>,
+) -> anyhow::Result<()> {
+    let mismatched: Vec<&str> = expected
+        .iter()
+        .filter(|(name, checksum)| actual.get(*name) != Some(checksum))
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    if mismatched.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Checksums don't match after copying: {}",
+            mismatched.join(", ")
+        ))
+    }
+}
+
+fn collect_checksums(
+    dir: &Path,
+    prefix: &str,
+    checksums: &mut BTreeMap<String, String>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_checksums(&entry.path(), &name, checksums)?;
+        } else if file_type.is_file() {
+            checksums.insert(name, file_checksum(&entry.path())?);
+        }
+    }
+    Ok(())
+}
+
+fn file_checksum(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut context = digest::Context::new(&digest::SHA256);
+    let mut buffer = [0; 65536];
+    loop {
+        let count = file.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+        context.update(&buffer[..count]);
+    }
+    Ok(context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
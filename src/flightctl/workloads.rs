@@ -0,0 +1,518 @@
+use super::kubeclient::{KubeClient, Selector};
+use futures::stream::{self, BoxStream};
+use futures::{FutureExt, StreamExt};
+use k8s_openapi::api::apps::v1 as apps;
+use k8s_openapi::api::batch::v1 as batch;
+use k8s_openapi::api::core::v1 as k8s;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+use kube::api::WatchEvent;
+use kube::Resource;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Resources which make up the processes running for a release.
+#[derive(Clone, Debug, Default)]
+pub struct Resources {
+    pub deployments: Vec<apps::Deployment>,
+    pub stateful_sets: Vec<apps::StatefulSet>,
+    pub cron_jobs: Vec<batch::CronJob>,
+    pub replica_sets: Vec<apps::ReplicaSet>,
+    pub jobs: Vec<batch::Job>,
+    pub pods: Vec<k8s::Pod>,
+}
+
+/// A change to one of the resources for a release.
+#[derive(Debug)]
+pub enum Change {
+    Deployment(Box<WatchEvent<apps::Deployment>>),
+    StatefulSet(Box<WatchEvent<apps::StatefulSet>>),
+    CronJob(Box<WatchEvent<batch::CronJob>>),
+    ReplicaSet(Box<WatchEvent<apps::ReplicaSet>>),
+    Job(Box<WatchEvent<batch::Job>>),
+    Pod(Box<WatchEvent<k8s::Pod>>),
+}
+
+impl Resources {
+    /// Updates the resources with a change from a watch.
+    pub fn apply(&mut self, change: Change) -> anyhow::Result<()> {
+        match change {
+            Change::Deployment(event) => update(&mut self.deployments, *event),
+            Change::StatefulSet(event) => update(&mut self.stateful_sets, *event),
+            Change::CronJob(event) => update(&mut self.cron_jobs, *event),
+            Change::ReplicaSet(event) => update(&mut self.replica_sets, *event),
+            Change::Job(event) => update(&mut self.jobs, *event),
+            Change::Pod(event) => update(&mut self.pods, *event),
+        }
+    }
+}
+
+/// A workload with the pods it currently owns.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workload {
+    pub kind: String,
+    pub name: String,
+
+    /// Pods the workload wants, or `None` for CronJobs.
+    pub desired: Option<i32>,
+    pub ready: i32,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub up_to_date: Option<i32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available: Option<i32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+    pub created: Option<Time>,
+    pub pods: Vec<Process>,
+}
+
+/// A pod and the state of its containers.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Process {
+    pub name: String,
+
+    /// What kubectl would show as the pod's status, such as `Running` or
+    /// `CrashLoopBackOff`.
+    pub status: String,
+    pub ready: usize,
+    pub containers: usize,
+    pub restarts: i32,
+    pub created: Option<Time>,
+    pub node: Option<String>,
+    pub images: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_termination: Option<Termination>,
+}
+
+/// Why a container last stopped, such as `OOMKilled`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Termination {
+    pub container: String,
+    pub reason: String,
+    pub exit_code: i32,
+    pub finished: Option<Time>,
+}
+
+/// Lists the Deployments, StatefulSets, CronJobs and pods matching a
+/// selector, along with the ReplicaSets and Jobs which connect them.
+pub fn fetch(client: &KubeClient, selector: &Selector) -> anyhow::Result<Resources> {
+    Ok(Resources {
+        deployments: client.list_resources(selector)?,
+        stateful_sets: client.list_resources(selector)?,
+        cron_jobs: client.list_resources(selector)?,
+        replica_sets: client.list_resources(selector)?,
+        jobs: client.list_resources(selector)?,
+        pods: client.list_resources(selector)?,
+    })
+}
+
+/// Watches the resources matching a selector, calling `on_update` with their
+/// current state after each batch of changes, and at least every `refresh`
+/// so that ages stay current. Watches closed by the API server are reopened,
+/// so this only returns when listing or `on_update` fails.
+///
+/// Each watch starts from a complete list of every kind of resource, and
+/// `on_update` is told when the resources were just listed afresh rather
+/// than changed, since anything new may have appeared while no watch was
+/// open.
+pub fn watch<F>(
+    client: &KubeClient,
+    selector: &Selector,
+    refresh: Duration,
+    mut on_update: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(&Resources, bool) -> anyhow::Result<()>,
+{
+    loop {
+        let (deployments, deployment_changes) =
+            list_and_watch(client, selector, Change::Deployment)?;
+        let (stateful_sets, stateful_set_changes) =
+            list_and_watch(client, selector, Change::StatefulSet)?;
+        let (cron_jobs, cron_job_changes) = list_and_watch(client, selector, Change::CronJob)?;
+        let (replica_sets, replica_set_changes) =
+            list_and_watch(client, selector, Change::ReplicaSet)?;
+        let (jobs, job_changes) = list_and_watch(client, selector, Change::Job)?;
+        let (pods, pod_changes) = list_and_watch(client, selector, Change::Pod)?;
+        let mut resources = Resources {
+            deployments,
+            stateful_sets,
+            cron_jobs,
+            replica_sets,
+            jobs,
+            pods,
+        };
+        on_update(&resources, true)?;
+
+        let ticks = stream::unfold((), move |_| async move {
+            tokio::time::sleep(refresh).await;
+            Some((Ok(Next::Tick), ()))
+        });
+        let mut events = stream::select_all(vec![
+            deployment_changes,
+            stateful_set_changes,
+            cron_job_changes,
+            replica_set_changes,
+            job_changes,
+            pod_changes,
+            ticks.boxed(),
+        ]);
+
+        let closed: Option<anyhow::Error> = client.block_on(async {
+            let mut next = events.next().await;
+            loop {
+                match next {
+                    Some(Ok(Next::Change(change))) => {
+                        if let Err(err) = resources.apply(change) {
+                            return anyhow::Ok(Some(err));
+                        }
+                    }
+                    Some(Ok(Next::Tick)) => {}
+                    Some(Ok(Next::Closed)) | None => return Ok(None),
+                    Some(Err(err)) => return Ok(Some(err)),
+                }
+                // Changes often arrive together, such as while replaying, so
+                // only update once there are none waiting.
+                next = match events.next().now_or_never() {
+                    Some(waiting) => waiting,
+                    None => {
+                        on_update(&resources, false)?;
+                        events.next().await
+                    }
+                };
+            }
+        })??;
+        match closed {
+            None => log::debug!("Watch closed; reopening"),
+            Some(err) => {
+                log::warn!("Watch failed: {:#}; reopening", err);
+                std::thread::sleep(refresh);
+            }
+        }
+    }
+}
+
+enum Next {
+    Change(Change),
+    Closed,
+    Tick,
+}
+
+fn list_and_watch<K, W>(
+    client: &KubeClient,
+    selector: &Selector,
+    wrap: W,
+) -> anyhow::Result<(Vec<K>, BoxStream<'static, anyhow::Result<Next>>)>
+where
+    K: Resource<Scope = k8s_openapi::NamespaceResourceScope>
+        + Clone
+        + serde::de::DeserializeOwned
+        + std::fmt::Debug
+        + Send
+        + 'static,
+    K::DynamicType: Default,
+    W: Fn(Box<WatchEvent<K>>) -> Change + Send + 'static,
+{
+    let (resources, changes) = client.list_and_watch::<K>(selector)?;
+    let changes = changes
+        .map(move |event| event.map(|event| Next::Change(wrap(Box::new(event)))))
+        .chain(stream::once(async { Ok(Next::Closed) }));
+    Ok((resources, changes.boxed()))
+}
+
+fn update<K: Resource>(resources: &mut Vec<K>, event: WatchEvent<K>) -> anyhow::Result<()> {
+    match event {
+        WatchEvent::Added(resource) | WatchEvent::Modified(resource) => {
+            let name = resource.meta().name.clone();
+            match resources.iter_mut().find(|r| r.meta().name == name) {
+                Some(existing) => *existing = resource,
+                None => resources.push(resource),
+            }
+        }
+        WatchEvent::Deleted(resource) => {
+            resources.retain(|r| r.meta().name != resource.meta().name);
+        }
+        WatchEvent::Bookmark(_) => {}
+        WatchEvent::Error(err) => return Err(anyhow::Error::from(err)),
+    }
+    Ok(())
+}
+
+/// Groups pods under the workloads which own them, through ReplicaSets for
+/// Deployments and Jobs for CronJobs. Jobs without a CronJob and pods without
+/// a workload are listed on their own.
+pub fn group(resources: Resources) -> Vec<Workload> {
+    let mut owners: HashMap<(String, String), (String, String)> = HashMap::new();
+    for replica_set in &resources.replica_sets {
+        if let Some(owner) = owner(&replica_set.metadata, "Deployment") {
+            owners.insert(
+                (String::from("ReplicaSet"), name(&replica_set.metadata)),
+                (String::from("Deployment"), owner),
+            );
+        }
+    }
+    for job in &resources.jobs {
+        if let Some(owner) = owner(&job.metadata, "CronJob") {
+            owners.insert(
+                (String::from("Job"), name(&job.metadata)),
+                (String::from("CronJob"), owner),
+            );
+        }
+    }
+
+    let mut resources = resources;
+    sort_by_name(&mut resources.deployments);
+    sort_by_name(&mut resources.stateful_sets);
+    sort_by_name(&mut resources.cron_jobs);
+    sort_by_name(&mut resources.jobs);
+
+    let mut workloads: Vec<Workload> = Vec::new();
+    workloads.extend(resources.deployments.into_iter().map(deployment));
+    workloads.extend(resources.stateful_sets.into_iter().map(stateful_set));
+    workloads.extend(resources.cron_jobs.into_iter().map(cron_job));
+    workloads.extend(
+        resources
+            .jobs
+            .into_iter()
+            .filter(|job| owner(&job.metadata, "CronJob").is_none())
+            .map(job),
+    );
+
+    let mut unowned = Vec::new();
+    for pod in resources.pods {
+        let index = pod
+            .metadata
+            .owner_references
+            .iter()
+            .flatten()
+            .map(|reference| (reference.kind.clone(), reference.name.clone()))
+            .map(|key| owners.get(&key).cloned().unwrap_or(key))
+            .find_map(|(kind, name)| {
+                workloads
+                    .iter()
+                    .position(|workload| workload.kind == kind && workload.name == name)
+            });
+        match index {
+            Some(index) => workloads[index].pods.push(process(pod)),
+            None => unowned.push(pod),
+        }
+    }
+
+    workloads.extend(unowned.into_iter().map(|pod| Workload {
+        kind: String::from("Pod"),
+        name: name(&pod.metadata),
+        desired: Some(1),
+        ready: i32::from(is_ready(&pod)),
+        up_to_date: None,
+        available: None,
+        schedule: None,
+        created: pod.metadata.creation_timestamp.clone(),
+        pods: vec![process(pod)],
+    }));
+    for workload in &mut workloads {
+        workload.pods.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    workloads
+}
+
+/// Shortens an image reference to its tag, such as `v1.2.3` for
+/// `registry.example.com/app:v1.2.3`, or the start of its digest.
+pub fn image_tag(image: &str) -> String {
+    if let Some((_, digest)) = image.split_once('@') {
+        let hash = digest.split_once(':').map_or(digest, |(_, hash)| hash);
+        return format!("@{}", &hash[..hash.len().min(12)]);
+    }
+    let name = image.rsplit('/').next().unwrap_or(image);
+    match name.split_once(':') {
+        Some((_, tag)) => String::from(tag),
+        None => String::from("latest"),
+    }
+}
+
+/// Summarizes a pod's status the way kubectl does: the reason a container
+/// is waiting or terminated takes precedence over the pod's phase.
+pub fn pod_status(pod: &k8s::Pod) -> String {
+    if pod.metadata.deletion_timestamp.is_some() {
+        return String::from("Terminating");
+    }
+    let status = match &pod.status {
+        Some(status) => status,
+        None => return String::from("Unknown"),
+    };
+    let containers = status
+        .init_container_statuses
+        .iter()
+        .flatten()
+        .chain(status.container_statuses.iter().flatten());
+    for container in containers {
+        if let Some(state) = &container.state {
+            if let Some(reason) = state.waiting.as_ref().and_then(|w| w.reason.clone()) {
+                if reason != "PodInitializing" && reason != "ContainerCreating" {
+                    return reason;
+                }
+            }
+            if let Some(terminated) = &state.terminated {
+                if terminated.exit_code != 0 {
+                    return terminated
+                        .reason
+                        .clone()
+                        .unwrap_or_else(|| format!("ExitCode:{}", terminated.exit_code));
+                }
+            }
+        }
+    }
+    status
+        .reason
+        .clone()
+        .or_else(|| status.phase.clone())
+        .unwrap_or_else(|| String::from("Unknown"))
+}
+
+/// Finds the most recent time any of a pod's containers stopped.
+pub fn last_termination(pod: &k8s::Pod) -> Option<Termination> {
+    pod.status
+        .as_ref()?
+        .container_statuses
+        .iter()
+        .flatten()
+        .filter_map(|container| {
+            let terminated = container.last_state.as_ref()?.terminated.as_ref()?;
+            Some(Termination {
+                container: container.name.clone(),
+                reason: terminated
+                    .reason
+                    .clone()
+                    .unwrap_or_else(|| String::from("Unknown")),
+                exit_code: terminated.exit_code,
+                finished: terminated.finished_at.clone(),
+            })
+        })
+        .max_by(|a, b| {
+            let finished = |termination: &Termination| termination.finished.as_ref().map(|t| t.0);
+            finished(a).cmp(&finished(b))
+        })
+}
+
+fn process(pod: k8s::Pod) -> Process {
+    let status = pod_status(&pod);
+    let last_termination = last_termination(&pod);
+    let containers = pod
+        .status
+        .as_ref()
+        .and_then(|status| status.container_statuses.clone())
+        .unwrap_or_default();
+    let spec = pod.spec.unwrap_or_default();
+    Process {
+        name: pod.metadata.name.unwrap_or_default(),
+        status,
+        ready: containers.iter().filter(|c| c.ready).count(),
+        containers: spec.containers.len(),
+        restarts: containers.iter().map(|c| c.restart_count).sum(),
+        created: pod.metadata.creation_timestamp,
+        node: spec.node_name,
+        images: spec
+            .containers
+            .into_iter()
+            .filter_map(|container| container.image)
+            .collect(),
+        last_termination,
+    }
+}
+
+fn deployment(deployment: apps::Deployment) -> Workload {
+    let status = deployment.status.unwrap_or_default();
+    Workload {
+        kind: String::from("Deployment"),
+        name: deployment.metadata.name.unwrap_or_default(),
+        desired: Some(deployment.spec.and_then(|spec| spec.replicas).unwrap_or(1)),
+        ready: status.ready_replicas.unwrap_or(0),
+        up_to_date: Some(status.updated_replicas.unwrap_or(0)),
+        available: Some(status.available_replicas.unwrap_or(0)),
+        schedule: None,
+        created: deployment.metadata.creation_timestamp,
+        pods: Vec::new(),
+    }
+}
+
+fn stateful_set(stateful_set: apps::StatefulSet) -> Workload {
+    let status = stateful_set.status.unwrap_or_default();
+    Workload {
+        kind: String::from("StatefulSet"),
+        name: stateful_set.metadata.name.unwrap_or_default(),
+        desired: Some(
+            stateful_set
+                .spec
+                .and_then(|spec| spec.replicas)
+                .unwrap_or(1),
+        ),
+        ready: status.ready_replicas.unwrap_or(0),
+        up_to_date: Some(status.updated_replicas.unwrap_or(0)),
+        available: Some(status.available_replicas.unwrap_or(0)),
+        schedule: None,
+        created: stateful_set.metadata.creation_timestamp,
+        pods: Vec::new(),
+    }
+}
+
+fn cron_job(cron_job: batch::CronJob) -> Workload {
+    Workload {
+        kind: String::from("CronJob"),
+        name: cron_job.metadata.name.unwrap_or_default(),
+        desired: None,
+        ready: cron_job
+            .status
+            .and_then(|status| status.active)
+            .map_or(0, |active| active.len() as i32),
+        up_to_date: None,
+        available: None,
+        schedule: cron_job.spec.map(|spec| spec.schedule),
+        created: cron_job.metadata.creation_timestamp,
+        pods: Vec::new(),
+    }
+}
+
+fn job(job: batch::Job) -> Workload {
+    let status = job.status.unwrap_or_default();
+    Workload {
+        kind: String::from("Job"),
+        name: job.metadata.name.unwrap_or_default(),
+        desired: Some(job.spec.and_then(|spec| spec.completions).unwrap_or(1)),
+        ready: status.succeeded.unwrap_or(0),
+        up_to_date: None,
+        available: None,
+        schedule: None,
+        created: job.metadata.creation_timestamp,
+        pods: Vec::new(),
+    }
+}
+
+fn owner(metadata: &ObjectMeta, kind: &str) -> Option<String> {
+    metadata
+        .owner_references
+        .iter()
+        .flatten()
+        .find(|reference| reference.kind == kind)
+        .map(|reference| reference.name.clone())
+}
+
+fn sort_by_name<K: Resource>(resources: &mut [K]) {
+    resources.sort_by(|a, b| a.meta().name.cmp(&b.meta().name));
+}
+
+fn name(metadata: &ObjectMeta) -> String {
+    metadata.name.clone().unwrap_or_default()
+}
+
+fn is_ready(pod: &k8s::Pod) -> bool {
+    pod.status
+        .iter()
+        .flat_map(|status| status.conditions.iter().flatten())
+        .any(|condition| condition.type_ == "Ready" && condition.status == "True")
+}
@@ -0,0 +1,4 @@
+pub mod commands;
+mod flightctl;
+
+pub use crate::flightctl::*;
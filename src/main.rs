@@ -1,17 +1,22 @@
 use env_logger;
+use flightctl::commands;
+use flightctl::commands::output::OutputFormat;
+use flightctl::runner::{CommandRunner, SystemRunner};
 use flightctl::{Config, ConfigFile, Release, Selector};
 use log;
+use std::process;
 use structopt::StructOpt;
 
-mod commands;
-mod flightctl;
-
 #[derive(Debug, StructOpt)]
 #[structopt(name = "flightctl", about = "control a cloud workspace")]
 struct Opt {
     #[structopt(short, long)]
     debug: bool,
 
+    /// Output format: json, table or yaml
+    #[structopt(short, long, global = true, default_value = "table")]
+    output: OutputFormat,
+
     #[structopt(subcommand)]
     cmd: Option<Command>,
 
@@ -32,10 +37,80 @@ enum Command {
     Config {
         #[structopt(flatten)]
         selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::config::ConfigOptions,
+
+        #[structopt(subcommand)]
+        cmd: Option<ConfigCommand>,
     },
 
     /// Run a console for a release
     Console {
+        /// Console to open, as listed by `view consoles`
+        name: Option<String>,
+
+        #[structopt(flatten)]
+        selector: Selector,
+
+        #[structopt(flatten)]
+        tty: commands::console::TtyOptions,
+    },
+
+    /// Copy files to or from the console container for a release
+    Cp {
+        #[structopt(flatten)]
+        selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::copy::CopyOptions,
+    },
+
+    /// Connect to a database for a release through the cluster
+    Db {
+        #[structopt(subcommand)]
+        cmd: DbCommand,
+    },
+
+    /// Deploy manifests for a release and wait for rollouts
+    Deploy {
+        #[structopt(flatten)]
+        selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::deploy::DeployOptions,
+    },
+
+    /// Show changes a deploy would make to a release
+    ///
+    /// Exits with status 0 when there are no changes, 1 when there are
+    /// changes, and 2 when the diff couldn't be completed.
+    Diff {
+        #[structopt(flatten)]
+        selector: Selector,
+    },
+
+    /// List Kubernetes events for processes running for a release
+    ///
+    /// Shows events about the application's deployments, replica sets, jobs
+    /// and pods, such as scheduling, probe and image pull failures, oldest
+    /// first.
+    Events {
+        #[structopt(flatten)]
+        selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::events::EventsOptions,
+    },
+
+    /// Forward local ports to services or pods for a release
+    ///
+    /// Runs every forward configured for the application, or only those
+    /// named, reconnecting when pods are replaced until interrupted.
+    Forward {
+        /// Forwards to run, as configured in `forwards`
+        names: Vec<String>,
+
         #[structopt(flatten)]
         selector: Selector,
     },
@@ -47,10 +122,22 @@ enum Command {
         selector: Selector,
     },
 
+    /// Stream logs from processes running for a release
+    Logs {
+        #[structopt(flatten)]
+        selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::logs::LogOptions,
+    },
+
     /// List processes running for a release
     Ps {
         #[structopt(flatten)]
         selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::process::PsOptions,
     },
 
     /// Run a container command for a release
@@ -59,6 +146,12 @@ enum Command {
 
         #[structopt(flatten)]
         selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::job::RunOptions,
+
+        #[structopt(flatten)]
+        tty: commands::console::TtyOptions,
     },
 
     /// View information about this workspace
@@ -68,6 +161,76 @@ enum Command {
     },
 }
 
+#[derive(Debug, StructOpt)]
+enum ConfigCommand {
+    /// Compare configuration variables between two releases
+    Diff {
+        #[structopt(short, long)]
+        application: Option<String>,
+
+        /// Environments to compare, such as -e staging -e production
+        #[structopt(short, long, number_of_values = 1)]
+        environment: Vec<String>,
+
+        /// Fetch and compare secret values, showing them in the output and
+        /// recording the access in the audit log
+        #[structopt(long)]
+        reveal_secrets: bool,
+
+        /// Reveal secrets without asking for confirmation
+        #[structopt(long, requires = "reveal-secrets")]
+        yes: bool,
+    },
+
+    /// Print a single configuration variable
+    Get {
+        #[structopt(flatten)]
+        selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::config::GetOptions,
+    },
+
+    /// Set configuration variables in the ConfigMaps which provide them
+    Set {
+        #[structopt(flatten)]
+        selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::config::SetOptions,
+    },
+
+    /// Remove configuration variables from the ConfigMaps which provide them
+    Unset {
+        #[structopt(flatten)]
+        selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::config::UnsetOptions,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum DbCommand {
+    /// Run psql connected to a database, with credentials from the release
+    Psql {
+        #[structopt(flatten)]
+        selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::database::PsqlOptions,
+    },
+
+    /// Open a local tunnel to a database until interrupted
+    Tunnel {
+        #[structopt(flatten)]
+        selector: Selector,
+
+        #[structopt(flatten)]
+        options: commands::database::TunnelOptions,
+    },
+}
+
 #[derive(Debug, StructOpt)]
 enum ViewCommand {
     /// View applications for this workspace
@@ -79,6 +242,9 @@ enum ViewCommand {
     /// View clusters for this workspace
     Clusters,
 
+    /// View consoles for each application
+    Consoles,
+
     /// View contexts for this workspace
     Contexts,
 
@@ -87,16 +253,12 @@ enum ViewCommand {
 }
 
 fn preflight<'a>(
+    runner: &dyn CommandRunner,
     config: &'a Config,
     opt: &Opt,
     selector: &Selector,
 ) -> anyhow::Result<&'a Release> {
-    log::debug!("Beginning preflight");
-    let release = opt.selector.merge(selector).apply(&config)?;
-    flightctl::authorize::run(&config, &release)?;
-    flightctl::context::prepare(&config, &release)?;
-    log::debug!("Preflight complete");
-    Ok(release)
+    flightctl::preflight::run(runner, config, opt.selector.merge(selector))
 }
 
 fn init_logger(default: &str) {
@@ -109,6 +271,7 @@ fn main() -> anyhow::Result<()> {
     let opt = Opt::from_args();
     let config_file = ConfigFile::find()?;
     let config = config_file.config;
+    let runner = SystemRunner;
 
     if opt.debug {
         init_logger("debug");
@@ -127,50 +290,210 @@ fn main() -> anyhow::Result<()> {
             ref cmd,
             ref selector,
         }) => {
-            let release = preflight(&config, &opt, &selector)?;
-            commands::aws::run(&config, release, cmd)
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::aws::run(&runner, &config, release, cmd)
+        }
+        Some(Command::Config {
+            cmd:
+                Some(ConfigCommand::Diff {
+                    ref application,
+                    ref environment,
+                    reveal_secrets,
+                    yes,
+                }),
+            ..
+        }) => match environment.as_slice() {
+            [left, right] => {
+                let select = |environment: &String| Selector {
+                    application: application.clone(),
+                    environment: Some(environment.clone()),
+                };
+                let left = preflight(&runner, &config, &opt, &select(left))?;
+                let right = preflight(&runner, &config, &opt, &select(right))?;
+                commands::config::diff(
+                    &runner,
+                    &config,
+                    left,
+                    right,
+                    opt.output,
+                    reveal_secrets,
+                    yes,
+                )
+            }
+            _ => Err(anyhow::Error::msg(
+                "Specify exactly two environments to compare, such as -e staging -e production",
+            )),
+        },
+        Some(Command::Config {
+            ref selector,
+            cmd:
+                Some(ConfigCommand::Get {
+                    selector: ref get_selector,
+                    ref options,
+                }),
+            ..
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector.merge(get_selector))?;
+            commands::config::get(&runner, &config, release, options)
+        }
+        Some(Command::Config {
+            ref selector,
+            cmd:
+                Some(ConfigCommand::Set {
+                    selector: ref set_selector,
+                    ref options,
+                }),
+            ..
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector.merge(set_selector))?;
+            commands::config::set(&runner, &config, release, options)
+        }
+        Some(Command::Config {
+            ref selector,
+            cmd:
+                Some(ConfigCommand::Unset {
+                    selector: ref unset_selector,
+                    ref options,
+                }),
+            ..
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector.merge(unset_selector))?;
+            commands::config::unset(&runner, &config, release, options)
+        }
+        Some(Command::Config {
+            ref selector,
+            ref options,
+            cmd: None,
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::config::print(&runner, &config, release, opt.output, options)
+        }
+        Some(Command::Console {
+            ref name,
+            ref selector,
+            ref tty,
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::console::run(&runner, &config, release, name.as_deref(), tty.enabled())
+        }
+        Some(Command::Cp {
+            ref selector,
+            ref options,
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::copy::run(&runner, &config, release, options)
+        }
+        Some(Command::Db {
+            cmd:
+                DbCommand::Psql {
+                    ref selector,
+                    ref options,
+                },
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::database::psql(&runner, &config, release, options)
+        }
+        Some(Command::Db {
+            cmd:
+                DbCommand::Tunnel {
+                    ref selector,
+                    ref options,
+                },
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::database::tunnel(&runner, &config, release, options)
+        }
+        Some(Command::Deploy {
+            ref selector,
+            ref options,
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::deploy::run(&runner, &config, release, options)
         }
-        Some(Command::Config { ref selector }) => {
-            let release = preflight(&config, &opt, &selector)?;
-            commands::config::print(&config, release)
+        Some(Command::Diff { ref selector }) => {
+            let changed = preflight(&runner, &config, &opt, &selector)
+                .and_then(|release| commands::diff::run(&runner, &config, release))
+                .unwrap_or_else(|err| {
+                    eprintln!("Error: {:?}", err);
+                    process::exit(2)
+                });
+            if changed {
+                process::exit(1)
+            }
+            Ok(())
+        }
+        Some(Command::Events {
+            ref selector,
+            ref options,
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::events::run(&runner, &config, release, opt.output, options)
         }
-        Some(Command::Console { ref selector }) => {
-            let release = preflight(&config, &opt, &selector)?;
-            commands::console::run_default(&config, release)
+        Some(Command::Forward {
+            ref names,
+            ref selector,
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::forward::run(&runner, &config, release, names)
         }
         Some(Command::Kubectl {
             ref cmd,
             ref selector,
         }) => {
-            let release = preflight(&config, &opt, &selector)?;
-            commands::kubectl::run(&config, release, cmd)
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::kubectl::run(&runner, &config, release, cmd)
+        }
+        Some(Command::Logs {
+            ref selector,
+            ref options,
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::logs::run(&runner, &config, release, options)
         }
-        Some(Command::Ps { ref selector }) => {
-            let release = preflight(&config, &opt, &selector)?;
-            commands::process::run(&config, release)
+        Some(Command::Ps {
+            ref selector,
+            ref options,
+        }) => {
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            commands::process::run(&runner, &config, release, opt.output, options)
         }
         Some(Command::Run {
             ref cmd,
             ref selector,
+            ref options,
+            ref tty,
         }) => {
-            let release = preflight(&config, &opt, &selector)?;
-            commands::console::run_command(&config, release, cmd)
+            let release = preflight(&runner, &config, &opt, &selector)?;
+            if let Some(script) = &options.script {
+                commands::console::run_script(&runner, &config, release, script, cmd, tty.enabled())
+            } else if options.job || options.detach {
+                let code = commands::job::run(&runner, &config, release, cmd, options)?;
+                if code != 0 {
+                    process::exit(code)
+                }
+                Ok(())
+            } else {
+                commands::console::run_command(&runner, &config, release, cmd, tty.enabled())
+            }
         }
         Some(Command::View {
             cmd: ViewCommand::Applications,
-        }) => commands::view::applications(config),
+        }) => commands::view::applications(config, opt.output),
         Some(Command::View {
             cmd: ViewCommand::Auth,
-        }) => commands::view::auth(config),
+        }) => commands::view::auth(config, opt.output),
         Some(Command::View {
             cmd: ViewCommand::Clusters,
-        }) => commands::view::clusters(config),
+        }) => commands::view::clusters(config, opt.output),
+        Some(Command::View {
+            cmd: ViewCommand::Consoles,
+        }) => commands::view::consoles(config, opt.output),
         Some(Command::View {
             cmd: ViewCommand::Contexts,
-        }) => commands::view::contexts(config),
+        }) => commands::view::contexts(config, opt.output),
         Some(Command::View {
             cmd: ViewCommand::Releases,
-        }) => commands::view::releases(config),
+        }) => commands::view::releases(config, opt.output),
         None => {
             Opt::clap().print_help()?;
             println!("");
@@ -0,0 +1,38 @@
+use flightctl::audit::{self, Entry};
+
+#[test]
+fn appends_entries_as_json_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("state").join("audit.log");
+
+    audit::record(
+        &path,
+        &Entry::new(
+            "example-staging",
+            "example-staging",
+            "SECRET_KEY_BASE",
+            "secret/web.key",
+        ),
+    )
+    .unwrap();
+    audit::record(
+        &path,
+        &Entry::new(
+            "example-production",
+            "example-production",
+            "API_TOKEN",
+            "secret/api.token",
+        ),
+    )
+    .unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let entries: Vec<serde_json::Value> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["release"], "example-staging");
+    assert_eq!(entries[0]["source"], "secret/web.key");
+    assert_eq!(entries[1]["variable"], "API_TOKEN");
+}
@@ -0,0 +1,76 @@
+use std::process::Command;
+
+/// Prints help for a command, which makes clap check every argument
+/// definition in debug builds, such as for clashing short flags.
+fn help(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_flightctl"))
+        .args(args)
+        .arg("--help")
+        .output()
+        .expect("flightctl runs");
+    assert!(
+        output.status.success(),
+        "flightctl {} --help failed:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("help is UTF-8")
+}
+
+fn subcommands(help: &str) -> Vec<String> {
+    help.lines()
+        .skip_while(|line| !line.starts_with("SUBCOMMANDS:"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|name| *name != "help")
+        .map(String::from)
+        .collect()
+}
+
+fn check(args: &[&str]) -> usize {
+    let help = help(args);
+    let mut checked = 1;
+    for subcommand in subcommands(&help) {
+        let mut args = args.to_vec();
+        args.push(&subcommand);
+        checked += check(&args);
+    }
+    checked
+}
+
+#[test]
+fn help_works_for_every_command() {
+    let checked = check(&[]);
+
+    assert!(checked > 20, "only checked {} commands", checked);
+}
+
+#[test]
+fn skipping_confirmation_requires_revealing_secrets() {
+    for args in [&["config", "--yes"][..], &["config", "diff", "--yes"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_flightctl"))
+            .args(args)
+            .output()
+            .expect("flightctl runs");
+
+        assert!(!output.status.success(), "{:?} succeeded", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("--include-secrets") || stderr.contains("--reveal-secrets"),
+            "{}",
+            stderr
+        );
+    }
+}
+
+#[test]
+fn job_timeout_requires_running_a_job() {
+    let output = Command::new(env!("CARGO_BIN_EXE_flightctl"))
+        .args(["run", "--timeout", "30m", "--", "rails", "db:migrate"])
+        .output()
+        .expect("flightctl runs");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--job|--detach"), "{}", stderr);
+}
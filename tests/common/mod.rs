@@ -0,0 +1,64 @@
+#![allow(dead_code)]
+
+use flightctl::{Config, ConfigFile, Release, Selector};
+use k8s_openapi::api::core::v1 as k8s;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+pub fn load_config() -> Config {
+    // Every test reads the same kubeconfig, so setting this from parallel
+    // tests is harmless.
+    std::env::set_var(
+        "KUBECONFIG",
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/kubeconfig.yaml"
+        ),
+    );
+    let yaml = include_str!("../fixtures/flightctl.yaml");
+    serde_yaml::from_str(yaml).expect("valid fixture configuration")
+}
+
+/// Writes the fixture configuration into `dir`, using a local manifests
+/// repository beside it, and loads it from `cwd`, as flightctl would when run
+/// from there.
+pub fn load_config_with_local_manifests(dir: &Path, cwd: &Path) -> ConfigFile {
+    let yaml = include_str!("../fixtures/flightctl.yaml")
+        .replace("git@github.com:example/manifests.git", "manifests");
+    std::fs::write(dir.join("flightctl.yaml"), yaml).unwrap();
+    std::fs::create_dir_all(dir.join("manifests/overlays/staging")).unwrap();
+    std::fs::create_dir_all(cwd).unwrap();
+    ConfigFile::find_from(cwd.to_path_buf()).expect("valid configuration")
+}
+
+pub fn environment(name: &str) -> Selector {
+    Selector {
+        application: None,
+        environment: Some(String::from(name)),
+    }
+}
+
+pub fn find_release<'a>(config: &'a Config, name: &str) -> &'a Release {
+    config
+        .releases
+        .iter()
+        .find(|release| release.name == name)
+        .expect("release defined in fixture")
+}
+
+/// Builds a Kubernetes object from JSON, as the API server would return it.
+pub fn resource<T: DeserializeOwned>(value: serde_json::Value) -> T {
+    serde_json::from_value(value).expect("valid resource")
+}
+
+/// A running pod with only a name, for commands which exec into it.
+pub fn pod() -> k8s::Pod {
+    k8s::Pod {
+        metadata: ObjectMeta {
+            name: Some(String::from("example-web-abc12")),
+            ..ObjectMeta::default()
+        },
+        ..k8s::Pod::default()
+    }
+}
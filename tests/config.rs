@@ -0,0 +1,500 @@
+mod common;
+
+use flightctl::commands::config::{
+    apply_patches, compare, config_patches, export_values, parse_assignment, render_exports,
+    reveal_secrets, settings, show_change, Change, ExportFormat, Setting,
+};
+use flightctl::kubeclient;
+use flightctl::kubeenv::{ResolvedValue, ResolvedVar};
+use flightctl::runner::fake::FakeRunner;
+use k8s_openapi::api::core::v1 as k8s;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+fn var(name: &str, value: ResolvedValue) -> ResolvedVar {
+    ResolvedVar {
+        name: Rc::new(String::from(name)),
+        value,
+        overridden: false,
+    }
+}
+
+fn secret(name: &str, secret: &str, key: &str) -> ResolvedVar {
+    var(
+        name,
+        ResolvedValue::SecretKeyRef {
+            secret: Rc::new(String::from(secret)),
+            key: Rc::new(String::from(key)),
+        },
+    )
+}
+
+fn plain(name: &str, value: &str) -> ResolvedVar {
+    var(
+        name,
+        ResolvedValue::Pod {
+            value: String::from(value),
+            template: None,
+        },
+    )
+}
+
+fn vars() -> Vec<ResolvedVar> {
+    vec![
+        plain("PORT", "3000"),
+        secret("SECRET_KEY_BASE", "web", "key"),
+        secret("API_TOKEN", "api", "token"),
+    ]
+}
+
+fn lookup(secret: &str, key: &str) -> Option<Rc<String>> {
+    match (secret, key) {
+        ("web", "key") => Some(Rc::new(String::from("s3cret"))),
+        ("api", "token") => Some(Rc::new(String::from("t0ken"))),
+        _ => None,
+    }
+}
+
+fn audit_entries(path: &std::path::Path) -> Vec<serde_json::Value> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn reveal_records_each_secret_in_the_audit_log() {
+    let config = common::load_config();
+    let release = common::find_release(&config, "example-staging");
+    let dir = tempfile::tempdir().unwrap();
+    let audit_log = dir.path().join("audit.log");
+    let vars = vars();
+
+    let secrets = reveal_secrets(
+        release,
+        &vars.iter().collect::<Vec<_>>(),
+        true,
+        &audit_log,
+        lookup,
+    )
+    .unwrap();
+
+    assert_eq!(secrets.len(), 2);
+    assert_eq!(secrets["SECRET_KEY_BASE"], "s3cret");
+    assert_eq!(secrets["API_TOKEN"], "t0ken");
+    let entries = audit_entries(&audit_log);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["variable"], "SECRET_KEY_BASE");
+    assert_eq!(entries[0]["source"], "secret/web.key");
+    assert_eq!(entries[1]["variable"], "API_TOKEN");
+    assert_eq!(entries[1]["release"], "example-staging");
+}
+
+#[test]
+fn reveal_without_secrets_records_nothing() {
+    let config = common::load_config();
+    let release = common::find_release(&config, "example-staging");
+    let dir = tempfile::tempdir().unwrap();
+    let audit_log = dir.path().join("audit.log");
+    let port = plain("PORT", "3000");
+
+    let secrets = reveal_secrets(release, &[&port], false, &audit_log, lookup).unwrap();
+
+    assert!(secrets.is_empty());
+    assert!(!audit_log.exists());
+}
+
+#[test]
+fn include_secrets_exports_revealed_values() {
+    let secrets = HashMap::from([(String::from("SECRET_KEY_BASE"), String::from("s3cret"))]);
+
+    assert_eq!(
+        export_values(vars(), &secrets, true),
+        vec![
+            (String::from("PORT"), String::from("3000")),
+            (String::from("SECRET_KEY_BASE"), String::from("s3cret")),
+        ]
+    );
+    assert_eq!(
+        export_values(vars(), &secrets, false),
+        vec![(String::from("PORT"), String::from("3000"))]
+    );
+}
+
+#[test]
+fn json_export_omits_secrets_unless_included() {
+    let secrets = HashMap::from([(String::from("SECRET_KEY_BASE"), String::from("s3cret"))]);
+
+    let omitted = render_exports(&export_values(vars(), &secrets, false), ExportFormat::Json);
+    let included = render_exports(&export_values(vars(), &secrets, true), ExportFormat::Json);
+
+    assert_eq!(omitted.unwrap(), "{\n  \"PORT\": \"3000\"\n}\n");
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&included.unwrap()).unwrap(),
+        json!({"PORT": "3000", "SECRET_KEY_BASE": "s3cret"})
+    );
+}
+
+#[test]
+fn reveal_secrets_compares_revealed_values() {
+    let secrets = HashMap::from([(String::from("SECRET_KEY_BASE"), String::from("s3cret"))]);
+
+    let revealed = settings(vars(), &secrets);
+    let masked = settings(vars(), &HashMap::new());
+
+    assert_eq!(revealed["SECRET_KEY_BASE"].value.as_deref(), Some("s3cret"));
+    assert!(revealed["SECRET_KEY_BASE"].secret);
+    assert_eq!(masked["SECRET_KEY_BASE"].value, None);
+    assert_eq!(masked["PORT"].value.as_deref(), Some("3000"));
+}
+
+#[test]
+fn compares_field_references_by_what_they_refer_to() {
+    let field = |path: &str| {
+        var(
+            "POD_IP",
+            ResolvedValue::FieldRef {
+                path: String::from(path),
+                value: None,
+            },
+        )
+    };
+    let memory = |value: Option<&str>| {
+        var(
+            "MEMORY_LIMIT",
+            ResolvedValue::ResourceFieldRef {
+                resource: String::from("limits.memory"),
+                value: value.map(String::from),
+            },
+        )
+    };
+
+    let left = settings(
+        vec![field("status.podIP"), memory(Some("536870912"))],
+        &HashMap::new(),
+    );
+    let right = settings(vec![field("status.hostIP"), memory(None)], &HashMap::new());
+
+    assert_eq!(
+        left["POD_IP"].value.as_deref(),
+        Some("(unset) (reference to status.podIP)")
+    );
+    assert_eq!(
+        right["MEMORY_LIMIT"].value.as_deref(),
+        Some("(node allocatable) (reference to limits.memory)")
+    );
+    let changes = compare(left, right);
+    assert_eq!(changes.len(), 2);
+    assert!(changes
+        .iter()
+        .all(|change| change.change == Change::Changed));
+}
+
+fn config_map_var(name: &str, config_map: &str, key: &str) -> ResolvedVar {
+    var(
+        name,
+        ResolvedValue::ConfigMapKeyRef {
+            config_map: Rc::new(String::from(config_map)),
+            key: Rc::new(String::from(key)),
+            value: Some(Rc::new(String::from("value"))),
+        },
+    )
+}
+
+/// A container which imports configmap/web and references a key of
+/// configmap/shared directly.
+fn container() -> k8s::Container {
+    serde_json::from_value(json!({
+        "name": "web",
+        "envFrom": [{"configMapRef": {"name": "web"}}],
+        "env": [
+            {
+                "name": "REDIS_URL",
+                "valueFrom": {"configMapKeyRef": {"name": "shared", "key": "redis"}}
+            },
+            {
+                "name": "FEATURE_FLAGS",
+                "valueFrom": {
+                    "configMapKeyRef": {"name": "shared", "key": "flags", "optional": true}
+                }
+            }
+        ]
+    }))
+    .unwrap()
+}
+
+fn container_vars() -> Vec<ResolvedVar> {
+    vec![
+        config_map_var("RAILS_LOG_LEVEL", "web", "RAILS_LOG_LEVEL"),
+        config_map_var("REDIS_URL", "shared", "redis"),
+        config_map_var("FEATURE_FLAGS", "shared", "flags"),
+        secret("SECRET_KEY_BASE", "web", "key"),
+    ]
+}
+
+#[test]
+fn parses_assignments() {
+    assert_eq!(
+        parse_assignment("DATABASE_URL=postgres://db/app?a=b").unwrap(),
+        (
+            String::from("DATABASE_URL"),
+            String::from("postgres://db/app?a=b")
+        )
+    );
+    assert_eq!(
+        parse_assignment("EMPTY=").unwrap(),
+        (String::from("EMPTY"), String::new())
+    );
+    assert!(parse_assignment("MISSING_VALUE").is_err());
+    assert!(parse_assignment("=value").is_err());
+}
+
+#[test]
+fn patches_the_config_maps_providing_variables() {
+    let config = common::load_config();
+    let release = common::find_release(&config, "example-staging");
+    let changes = vec![
+        (String::from("RAILS_LOG_LEVEL"), Some(String::from("debug"))),
+        (
+            String::from("REDIS_URL"),
+            Some(String::from("redis://cache")),
+        ),
+        (String::from("FEATURE_FLAGS"), None),
+    ];
+
+    let patches = config_patches(&container(), &container_vars(), changes, None, release).unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&patches).unwrap(),
+        json!({
+            "shared": {"redis": "redis://cache", "flags": null},
+            "web": {"RAILS_LOG_LEVEL": "debug"}
+        })
+    );
+
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            r#"kubectl --context example-staging patch configmap/shared --type merge --patch {"data":{"redis":"redis://cache","flags":null}}"#,
+            "",
+        )
+        .succeed(
+            r#"kubectl --context example-staging patch configmap/web --type merge --patch {"data":{"RAILS_LOG_LEVEL":"debug"}}"#,
+            "",
+        );
+    apply_patches(&kubeclient::new(&runner, &release.context), &patches).unwrap();
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn adds_new_variables_to_the_given_config_map() {
+    let config = common::load_config();
+    let release = common::find_release(&config, "example-staging");
+    let changes = vec![
+        (String::from("RAILS_LOG_LEVEL"), Some(String::from("debug"))),
+        (String::from("NEW_FLAG"), Some(String::from("on"))),
+    ];
+
+    let patches = config_patches(
+        &container(),
+        &container_vars(),
+        changes,
+        Some("web"),
+        release,
+    )
+    .unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&patches).unwrap(),
+        json!({"web": {"RAILS_LOG_LEVEL": "debug", "NEW_FLAG": "on"}})
+    );
+}
+
+#[test]
+fn refuses_to_move_variables_to_another_config_map() {
+    let config = common::load_config();
+    let release = common::find_release(&config, "example-staging");
+
+    let error = config_patches(
+        &container(),
+        &container_vars(),
+        vec![(
+            String::from("REDIS_URL"),
+            Some(String::from("redis://cache")),
+        )],
+        Some("web"),
+        release,
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "REDIS_URL is already set from configmap/shared.redis, not configmap/web; \
+         unset it first to move it"
+    );
+}
+
+#[test]
+fn unsets_imported_variables() {
+    let config = common::load_config();
+    let release = common::find_release(&config, "example-staging");
+
+    let patches = config_patches(
+        &container(),
+        &container_vars(),
+        vec![(String::from("RAILS_LOG_LEVEL"), None)],
+        None,
+        release,
+    )
+    .unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&patches).unwrap(),
+        json!({"web": {"RAILS_LOG_LEVEL": null}})
+    );
+}
+
+#[test]
+fn refuses_to_unset_required_references() {
+    let config = common::load_config();
+    let release = common::find_release(&config, "example-staging");
+
+    let error = config_patches(
+        &container(),
+        &container_vars(),
+        vec![(String::from("REDIS_URL"), None)],
+        None,
+        release,
+    )
+    .unwrap_err();
+
+    assert!(error.to_string().contains("required by container web"));
+    assert!(error.to_string().contains("configmap/shared.redis"));
+}
+
+#[test]
+fn refuses_to_add_to_config_maps_which_are_not_imported() {
+    let config = common::load_config();
+    let release = common::find_release(&config, "example-staging");
+
+    let error = config_patches(
+        &container(),
+        &container_vars(),
+        vec![(String::from("NEW_FLAG"), Some(String::from("on")))],
+        Some("shared"),
+        release,
+    )
+    .unwrap_err();
+
+    assert!(error
+        .to_string()
+        .contains("configmap/shared isn't imported"));
+}
+
+#[test]
+fn refuses_to_set_secrets() {
+    let config = common::load_config();
+    let release = common::find_release(&config, "example-staging");
+
+    let error = config_patches(
+        &container(),
+        &container_vars(),
+        vec![(String::from("SECRET_KEY_BASE"), Some(String::from("new")))],
+        None,
+        release,
+    )
+    .unwrap_err();
+
+    assert!(error.to_string().contains("not a ConfigMap"));
+}
+
+fn setting(source: &str, value: Option<&str>) -> Setting {
+    Setting {
+        source: String::from(source),
+        value: value.map(String::from),
+        secret: source.starts_with("secret/"),
+    }
+}
+
+fn release_settings(settings: Vec<(&str, Setting)>) -> BTreeMap<String, Setting> {
+    settings
+        .into_iter()
+        .map(|(name, setting)| (String::from(name), setting))
+        .collect()
+}
+
+#[test]
+fn compares_values_and_sources() {
+    let left = release_settings(vec![
+        (
+            "LOG_LEVEL",
+            setting("configmap/web-7h2k9.LOG_LEVEL", Some("info")),
+        ),
+        ("PORT", setting("configmap/web-7h2k9.PORT", Some("3000"))),
+        ("HOST", setting("configmap/web-7h2k9.HOST", Some("0.0.0.0"))),
+        ("REGION", setting("pod", Some("us-east-1"))),
+        ("API_TOKEN", setting("secret/api-staging.token", None)),
+        ("OLD", setting("pod", Some("1"))),
+        ("SAME", setting("pod", Some("same"))),
+    ]);
+    let right = release_settings(vec![
+        (
+            "LOG_LEVEL",
+            setting("configmap/web-c84fm.LOG_LEVEL", Some("warn")),
+        ),
+        ("PORT", setting("configmap/web-c84fm.PORT", Some("3000"))),
+        ("HOST", setting("pod", Some("0.0.0.0"))),
+        ("REGION", setting("pod", Some("us-west-2"))),
+        ("API_TOKEN", setting("secret/api-production.token", None)),
+        ("NEW", setting("pod", Some("1"))),
+        ("SAME", setting("pod", Some("same"))),
+    ]);
+
+    let changes = compare(left, right);
+
+    let summary: Vec<(&str, &Change)> = changes
+        .iter()
+        .map(|change| (change.name.as_str(), &change.change))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("API_TOKEN", &Change::Changed),
+            ("HOST", &Change::Changed),
+            ("LOG_LEVEL", &Change::Changed),
+            ("NEW", &Change::Added),
+            ("OLD", &Change::Removed),
+            ("PORT", &Change::Renamed),
+            ("REGION", &Change::Changed),
+        ]
+    );
+    let lines: Vec<String> = changes.iter().filter_map(show_change).collect();
+    assert_eq!(
+        lines[5],
+        "= PORT: 3000 (from configmap/web-7h2k9.PORT -> configmap/web-c84fm.PORT)"
+    );
+    assert_eq!(
+        lines[2],
+        "~ LOG_LEVEL: info (from configmap/web-7h2k9.LOG_LEVEL) -> \
+         warn (from configmap/web-c84fm.LOG_LEVEL)"
+    );
+}
+
+#[test]
+fn compares_revealed_secrets_by_value() {
+    let left = release_settings(vec![(
+        "API_TOKEN",
+        setting("secret/api-9f8d.token", Some("t0ken")),
+    )]);
+    let right = release_settings(vec![(
+        "API_TOKEN",
+        setting("secret/api-2c4b.token", Some("t0ken")),
+    )]);
+
+    let changes = compare(left, right);
+
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].change, Change::Renamed);
+}
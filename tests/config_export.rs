@@ -0,0 +1,52 @@
+use flightctl::commands::config::{render_exports, ExportFormat};
+
+fn values() -> Vec<(String, String)> {
+    [
+        ("DATABASE_URL", "postgres://db.internal:5432/app"),
+        ("EMPTY", ""),
+        ("GREETING", "hello world"),
+        ("PASSWORD", "it's $ecret"),
+        ("PEM", "line one\nline two"),
+    ]
+    .iter()
+    .map(|(name, value)| (String::from(*name), String::from(*value)))
+    .collect()
+}
+
+#[test]
+fn renders_dotenv() {
+    let rendered = render_exports(&values(), ExportFormat::Dotenv).unwrap();
+
+    assert_eq!(
+        rendered,
+        "DATABASE_URL=postgres://db.internal:5432/app\n\
+         EMPTY=''\n\
+         GREETING='hello world'\n\
+         PASSWORD=\"it's \\$ecret\"\n\
+         PEM=\"line one\\nline two\"\n"
+    );
+}
+
+#[test]
+fn renders_shell_exports() {
+    let rendered = render_exports(&values(), ExportFormat::Shell).unwrap();
+
+    assert_eq!(
+        rendered,
+        "export DATABASE_URL=postgres://db.internal:5432/app\n\
+         export EMPTY=''\n\
+         export GREETING='hello world'\n\
+         export PASSWORD='it'\\''s $ecret'\n\
+         export PEM='line one\nline two'\n"
+    );
+}
+
+#[test]
+fn renders_json() {
+    let rendered = render_exports(&values(), ExportFormat::Json).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+    assert_eq!(parsed["PASSWORD"], "it's $ecret");
+    assert_eq!(parsed["PEM"], "line one\nline two");
+    assert_eq!(parsed.as_object().unwrap().len(), 5);
+}
@@ -0,0 +1,196 @@
+mod common;
+
+use flightctl::commands::console::{self, TtyOptions};
+use flightctl::kubeclient;
+use flightctl::podspec;
+use flightctl::runner::fake::FakeRunner;
+use k8s_openapi::api::core::v1 as k8s;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use std::collections::BTreeMap;
+use structopt::StructOpt;
+
+fn template() -> k8s::Pod {
+    let labels = BTreeMap::from([(
+        String::from("app.kubernetes.io/name"),
+        String::from("example"),
+    )]);
+    k8s::Pod {
+        metadata: ObjectMeta {
+            labels: Some(labels),
+            namespace: Some(String::from("example-staging")),
+            ..ObjectMeta::default()
+        },
+        spec: Some(k8s::PodSpec {
+            containers: vec![k8s::Container {
+                name: String::from("main"),
+                image: Some(String::from("example:abc123")),
+                args: Some(vec![String::from("puma")]),
+                readiness_probe: Some(k8s::Probe::default()),
+                ..k8s::Container::default()
+            }],
+            service_account_name: Some(String::from("example")),
+            ..k8s::PodSpec::default()
+        }),
+        status: None,
+    }
+}
+
+fn command() -> Vec<String> {
+    vec![String::from("rails"), String::from("console")]
+}
+
+#[test]
+fn builds_console_pod_from_template() {
+    let pod =
+        podspec::console_pod(template(), "example-web", "main", &command(), None, true).unwrap();
+
+    assert_eq!(
+        pod.metadata.generate_name.as_deref(),
+        Some("example-web-console-")
+    );
+    let labels = pod.metadata.labels.unwrap();
+    assert!(!labels.contains_key("app.kubernetes.io/name"));
+    assert_eq!(labels["app.kubernetes.io/managed-by"], "flightctl");
+
+    let spec = pod.spec.unwrap();
+    assert_eq!(spec.restart_policy.as_deref(), Some("Never"));
+    assert_eq!(spec.service_account_name.as_deref(), Some("example"));
+    let container = &spec.containers[0];
+    assert_eq!(container.image.as_deref(), Some("example:abc123"));
+    assert_eq!(container.command, Some(command()));
+    assert_eq!(container.args, None);
+    assert_eq!(container.tty, Some(true));
+    assert_eq!(container.stdin_once, Some(true));
+    assert!(container.readiness_probe.is_none());
+}
+
+#[test]
+fn fails_for_unknown_container() {
+    let result = podspec::console_pod(template(), "example-web", "worker", &command(), None, true);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn attaches_debug_container_to_pod() {
+    let runner = FakeRunner::new();
+    runner.succeed(
+        "kubectl --context example-staging debug --stdin --tty example-web-abc12 --target=main --image=busybox -- sh",
+        "",
+    );
+    let client = kubeclient::new(&runner, "example-staging");
+    let pod = k8s::Pod {
+        metadata: ObjectMeta {
+            name: Some(String::from("example-web-abc12")),
+            ..ObjectMeta::default()
+        },
+        ..k8s::Pod::default()
+    };
+
+    client
+        .debug(&pod, "main", "busybox", &["sh"], true)
+        .unwrap();
+
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn execs_without_tty_when_disabled() {
+    let runner = FakeRunner::new();
+    runner.succeed(
+        "kubectl --context example-staging exec --stdin example-web-abc12 --container main -- rails runner -",
+        "",
+    );
+    let client = kubeclient::new(&runner, "example-staging");
+    let pod = k8s::Pod {
+        metadata: ObjectMeta {
+            name: Some(String::from("example-web-abc12")),
+            ..ObjectMeta::default()
+        },
+        ..k8s::Pod::default()
+    };
+    let command = vec!["rails", "runner", "-"];
+
+    client.exec(&pod, "main", &command, false).unwrap();
+
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn tty_flags_override_detection() {
+    let tty = TtyOptions::from_iter(&["console", "--no-tty", "--tty"]);
+    let no_tty = TtyOptions::from_iter(&["console", "--tty", "--no-tty"]);
+
+    assert!(tty.enabled());
+    assert!(!no_tty.enabled());
+}
+
+#[test]
+fn uploads_runs_and_removes_scripts() {
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            "kubectl --context example-staging exec --stdin example-web-abc12 --container main -- sh -c",
+            "",
+        )
+        .succeed(
+            "kubectl --context example-staging exec --stdin example-web-abc12 --container main -- rails runner /tmp/fix.rb",
+            "",
+        )
+        .succeed(
+            "kubectl --context example-staging exec example-web-abc12 --container main -- rm -f /tmp/fix.rb",
+            "",
+        );
+    let client = kubeclient::new(&runner, "example-staging");
+    let interpreter = vec![String::from("rails"), String::from("runner")];
+    let mut script: &[u8] = b"User.count";
+
+    console::exec_script(
+        &client,
+        &common::pod(),
+        "main",
+        "/tmp/fix.rb",
+        &mut script,
+        &interpreter,
+        false,
+    )
+    .unwrap();
+
+    assert!(runner.pending().is_empty());
+    assert_eq!(runner.inputs(), vec![b"User.count".to_vec()]);
+}
+
+#[test]
+fn removes_scripts_which_fail() {
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            "kubectl --context example-staging exec --stdin example-web-abc12 --container main -- sh -c",
+            "",
+        )
+        .fail(
+            "kubectl --context example-staging exec --stdin example-web-abc12 --container main -- rails runner /tmp/fix.rb",
+            1,
+            "",
+        )
+        .succeed(
+            "kubectl --context example-staging exec example-web-abc12 --container main -- rm -f /tmp/fix.rb",
+            "",
+        );
+    let client = kubeclient::new(&runner, "example-staging");
+    let interpreter = vec![String::from("rails"), String::from("runner")];
+    let mut script: &[u8] = b"raise";
+
+    let result = console::exec_script(
+        &client,
+        &common::pod(),
+        "main",
+        "/tmp/fix.rb",
+        &mut script,
+        &interpreter,
+        false,
+    );
+
+    assert!(result.is_err());
+    assert!(runner.pending().is_empty());
+}
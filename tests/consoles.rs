@@ -0,0 +1,59 @@
+mod common;
+
+use common::load_config;
+use flightctl::{Application, Console};
+
+fn container(console: &Console) -> &str {
+    match console {
+        Console::Exec { container, .. }
+        | Console::Run { container, .. }
+        | Console::Debug { container, .. } => container,
+    }
+}
+
+fn application(config: &flightctl::Config) -> &Application {
+    config
+        .applications
+        .iter()
+        .find(|application| application.name == "example")
+        .expect("application defined in fixture")
+}
+
+#[test]
+fn lists_default_console_first() {
+    let config = load_config();
+    let names: Vec<&str> = application(&config)
+        .consoles()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+
+    assert_eq!(names, vec!["default", "shell", "worker"]);
+}
+
+#[test]
+fn finds_consoles_by_name() {
+    let config = load_config();
+    let application = application(&config);
+
+    let console = application.find_console(Some("worker")).unwrap();
+    assert_eq!(container(console), "worker");
+
+    let console = application.find_console(None).unwrap();
+    match console {
+        Console::Exec { command, .. }
+        | Console::Run { command, .. }
+        | Console::Debug { command, .. } => {
+            assert_eq!(command.last().unwrap(), "console")
+        }
+    }
+}
+
+#[test]
+fn lists_consoles_when_name_is_unknown() {
+    let config = load_config();
+
+    let error = application(&config).find_console(Some("psql")).unwrap_err();
+
+    assert!(error.to_string().contains("default, shell, worker"));
+}
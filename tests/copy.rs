@@ -0,0 +1,129 @@
+mod common;
+
+use flightctl::commands::copy;
+use flightctl::kubeclient;
+use flightctl::runner::fake::FakeRunner;
+use flightctl::transfer;
+use std::fs;
+
+const HELLO_SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+#[test]
+fn uploads_and_verifies_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let local = dir.path().join("fix.csv");
+    fs::write(&local, "hello\n").unwrap();
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            "kubectl --context example-staging exec --stdin example-web-abc12 --container main -- sh -c",
+            "",
+        )
+        .succeed(
+            "kubectl --context example-staging exec example-web-abc12 --container main -- sh -c",
+            &format!("{}  /tmp/fix.csv\n", HELLO_SHA256),
+        );
+    let client = kubeclient::new(&runner, "example-staging");
+
+    copy::upload(
+        &runner,
+        &client,
+        &common::pod(),
+        "main",
+        &local,
+        "/tmp/",
+        true,
+    )
+    .unwrap();
+
+    assert!(runner.pending().is_empty());
+    assert_eq!(runner.inputs(), vec![b"hello\n".to_vec()]);
+    assert!(runner.calls()[0].ends_with("/tmp/fix.csv"));
+}
+
+#[test]
+fn fails_when_checksums_differ() {
+    let dir = tempfile::tempdir().unwrap();
+    let local = dir.path().join("fix.csv");
+    fs::write(&local, "hello\n").unwrap();
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            "kubectl --context example-staging exec --stdin example-web-abc12 --container main -- sh -c",
+            "",
+        )
+        .succeed(
+            "kubectl --context example-staging exec example-web-abc12 --container main -- sh -c",
+            "0000  /tmp/fix.csv\n",
+        );
+    let client = kubeclient::new(&runner, "example-staging");
+
+    let error = copy::upload(
+        &runner,
+        &client,
+        &common::pod(),
+        "main",
+        &local,
+        "/tmp/fix.csv",
+        true,
+    )
+    .unwrap_err();
+
+    assert!(error.to_string().contains("Checksums don't match"));
+}
+
+#[test]
+fn downloads_files_into_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            "kubectl --context example-staging exec example-web-abc12 --container main -- sh -c",
+            "file 6\n",
+        )
+        .succeed(
+            "kubectl --context example-staging exec example-web-abc12 --container main -- cat /tmp/export.csv",
+            "hello\n",
+        )
+        .succeed(
+            "kubectl --context example-staging exec example-web-abc12 --container main -- sh -c",
+            &format!("{}  /tmp/export.csv\n", HELLO_SHA256),
+        );
+    let client = kubeclient::new(&runner, "example-staging");
+
+    copy::download(
+        &runner,
+        &client,
+        &common::pod(),
+        "main",
+        "/tmp/export.csv",
+        dir.path(),
+        true,
+    )
+    .unwrap();
+
+    let contents = fs::read_to_string(dir.path().join("export.csv")).unwrap();
+    assert_eq!(contents, "hello\n");
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn computes_checksums_like_sha256sum() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("nested")).unwrap();
+    fs::write(dir.path().join("nested").join("hello.txt"), "hello\n").unwrap();
+
+    let local = transfer::local_checksums(dir.path()).unwrap();
+    let remote =
+        transfer::parse_checksums(&format!("{}  ./nested/hello.txt\n", HELLO_SHA256), false);
+
+    assert_eq!(local, remote);
+    assert!(transfer::verify(&remote, &local).is_ok());
+}
+
+#[test]
+fn formats_sizes() {
+    assert_eq!(transfer::format_bytes(512), "512 B");
+    assert_eq!(transfer::format_bytes(1536), "1.5 KiB");
+    assert_eq!(transfer::format_bytes(5 * 1024 * 1024), "5.0 MiB");
+}
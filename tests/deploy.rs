@@ -0,0 +1,142 @@
+mod common;
+
+use common::{find_release, load_config, load_config_with_local_manifests};
+use flightctl::commands::deploy::{self, DeployOptions};
+use flightctl::runner::fake::FakeRunner;
+
+const MANIFESTS: &str = "\
+apiVersion: v1
+kind: Service
+metadata:
+  name: web
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: worker
+";
+
+fn options() -> DeployOptions {
+    DeployOptions {
+        timeout: String::from("5m"),
+    }
+}
+
+#[test]
+fn applies_manifests_and_waits_for_rollouts() {
+    let config = load_config();
+    let release = find_release(&config, "example-staging");
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            "kubectl kustomize git@github.com:example/manifests.git//overlays/staging",
+            MANIFESTS,
+        )
+        .succeed("kubectl --context example-staging apply --filename", "")
+        .succeed(
+            "kubectl --context example-staging rollout status deployment/web --timeout 5m",
+            "",
+        )
+        .succeed(
+            "kubectl --context example-staging rollout status deployment/worker --timeout 5m",
+            "",
+        );
+
+    deploy::run(&runner, &config, release, &options()).unwrap();
+
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn fails_when_a_rollout_fails() {
+    let config = load_config();
+    let release = find_release(&config, "example-staging");
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            "kubectl kustomize git@github.com:example/manifests.git//overlays/staging",
+            MANIFESTS,
+        )
+        .succeed("kubectl --context example-staging apply --filename", "")
+        .fail(
+            "kubectl --context example-staging rollout status deployment/web --timeout 5m",
+            1,
+            "",
+        )
+        .succeed(
+            "kubectl --context example-staging rollout status deployment/worker --timeout 5m",
+            "",
+        );
+
+    let error = deploy::run(&runner, &config, release, &options()).unwrap_err();
+
+    assert!(error.to_string().contains("deployment/web"));
+    assert!(!error.to_string().contains("deployment/worker"));
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn does_not_wait_when_apply_fails() {
+    let config = load_config();
+    let release = find_release(&config, "example-staging");
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            "kubectl kustomize git@github.com:example/manifests.git//overlays/staging",
+            MANIFESTS,
+        )
+        .fail("kubectl --context example-staging apply --filename", 1, "");
+
+    let result = deploy::run(&runner, &config, release, &options());
+
+    assert!(result.is_err());
+    assert!(runner.calls().iter().all(|call| !call.contains("rollout")));
+}
+
+#[test]
+fn waits_for_rollouts_in_the_namespace_from_their_manifests() {
+    let config = load_config();
+    let release = find_release(&config, "example-staging");
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            "kubectl kustomize git@github.com:example/manifests.git//overlays/staging",
+            "\
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+  namespace: example-jobs
+",
+        )
+        .succeed("kubectl --context example-staging apply --filename", "")
+        .succeed(
+            "kubectl --context example-staging --namespace example-jobs rollout status deployment/web --timeout 5m",
+            "",
+        );
+
+    deploy::run(&runner, &config, release, &options()).unwrap();
+
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn builds_local_manifests_relative_to_the_configuration_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = load_config_with_local_manifests(dir.path(), &dir.path().join("app/src"));
+    let release = find_release(&config_file.config, "example-staging");
+    let target = dir.path().join("manifests/overlays/staging");
+    let runner = FakeRunner::new();
+    runner
+        .succeed(&format!("kubectl kustomize {}", target.display()), "")
+        .succeed("kubectl --context example-staging apply --filename", "");
+
+    deploy::run(&runner, &config_file.config, release, &options()).unwrap();
+
+    assert!(runner.pending().is_empty());
+}
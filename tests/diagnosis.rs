@@ -0,0 +1,431 @@
+mod common;
+
+use flightctl::commands::process;
+use flightctl::diagnosis;
+use k8s_openapi::api::batch::v1 as batch;
+use k8s_openapi::api::core::v1 as k8s;
+use serde_json::json;
+use std::collections::HashMap;
+
+fn pod(container: serde_json::Value, status: serde_json::Value) -> k8s::Pod {
+    common::resource(json!({
+        "metadata": {"name": "example-web-5d4f-b"},
+        "spec": {"containers": [container]},
+        "status": status
+    }))
+}
+
+fn event(pod: &str, reason: &str, message: &str, count: i32) -> k8s::Event {
+    common::resource(json!({
+        "metadata": {"name": format!("{}.{}", pod, reason)},
+        "involvedObject": {"kind": "Pod", "name": pod},
+        "reason": reason,
+        "message": message,
+        "count": count
+    }))
+}
+
+fn main_container() -> serde_json::Value {
+    json!({
+        "name": "main",
+        "image": "example:v2",
+        "resources": {"limits": {"memory": "512Mi"}, "requests": {"cpu": "2"}},
+        "livenessProbe": {"httpGet": {"path": "/health", "port": 3000}, "periodSeconds": 5}
+    })
+}
+
+#[test]
+fn crash_loop_shows_exit_code_and_logs() {
+    let pod = pod(
+        main_container(),
+        json!({
+            "phase": "Running",
+            "containerStatuses": [{
+                "name": "main",
+                "image": "example:v2",
+                "imageID": "",
+                "ready": false,
+                "restartCount": 4,
+                "state": {"waiting": {"reason": "CrashLoopBackOff"}},
+                "lastState": {"terminated": {"exitCode": 1, "reason": "Error"}}
+            }]
+        }),
+    );
+    assert_eq!(
+        diagnosis::crashed_containers(&pod),
+        vec![(String::from("main"), true)]
+    );
+
+    let logs = HashMap::from([(
+        String::from("main"),
+        vec![String::from("KeyError: DATABASE_URL")],
+    )]);
+    let findings = diagnosis::diagnose(&pod, &[], &logs);
+
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].problem, "CrashLoopBackOff");
+    assert_eq!(findings[0].container.as_deref(), Some("main"));
+    assert_eq!(
+        findings[0].evidence,
+        vec![
+            "Last exited with code 1 (Error)",
+            "Restarted 4 times",
+            "Last log lines from main before it stopped:",
+            "  | KeyError: DATABASE_URL",
+        ]
+    );
+}
+
+#[test]
+fn crashed_containers_are_waiting_to_restart_or_failed() {
+    let status = |restarts: i32, state: serde_json::Value| {
+        pod(
+            main_container(),
+            json!({
+                "phase": "Running",
+                "containerStatuses": [{
+                    "name": "main",
+                    "image": "example:v2",
+                    "imageID": "",
+                    "ready": false,
+                    "restartCount": restarts,
+                    "state": state,
+                    "lastState": {"terminated": {"exitCode": 1}}
+                }]
+            }),
+        )
+    };
+
+    let recovered = status(3, json!({"running": {}}));
+    let failed = status(0, json!({"terminated": {"exitCode": 1}}));
+    let finished = status(0, json!({"terminated": {"exitCode": 0}}));
+    let starting = status(0, json!({"waiting": {"reason": "ContainerCreating"}}));
+
+    assert!(diagnosis::crashed_containers(&recovered).is_empty());
+    assert_eq!(
+        diagnosis::crashed_containers(&failed),
+        vec![(String::from("main"), false)]
+    );
+    assert!(diagnosis::crashed_containers(&finished).is_empty());
+    assert!(diagnosis::crashed_containers(&starting).is_empty());
+}
+
+#[test]
+fn oom_killed_shows_memory_limit() {
+    let pod = pod(
+        main_container(),
+        json!({
+            "phase": "Running",
+            "containerStatuses": [{
+                "name": "main",
+                "image": "example:v2",
+                "imageID": "",
+                "ready": false,
+                "restartCount": 1,
+                "state": {"waiting": {"reason": "CrashLoopBackOff"}},
+                "lastState": {"terminated": {"exitCode": 137, "reason": "OOMKilled"}}
+            }]
+        }),
+    );
+
+    let findings = diagnosis::diagnose(&pod, &[], &HashMap::new());
+
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].problem, "OOMKilled");
+    assert_eq!(
+        findings[0].evidence,
+        vec!["Memory limit: 512Mi", "Restarted once"]
+    );
+}
+
+#[test]
+fn image_pull_failure_shows_image_and_events() {
+    let pod = pod(
+        main_container(),
+        json!({
+            "phase": "Pending",
+            "containerStatuses": [{
+                "name": "main",
+                "image": "example:v2",
+                "imageID": "",
+                "ready": false,
+                "restartCount": 0,
+                "state": {"waiting": {
+                    "reason": "ImagePullBackOff",
+                    "message": "Back-off pulling image \"example:v2\""
+                }}
+            }]
+        }),
+    );
+    let events = vec![
+        event(
+            "example-web-5d4f-b",
+            "Failed",
+            "Failed to pull image \"example:v2\": not found",
+            3,
+        ),
+        event("example-web-other", "Failed", "Unrelated", 1),
+    ];
+
+    let findings = diagnosis::diagnose(&pod, &events, &HashMap::new());
+
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].problem, "ImagePullBackOff");
+    assert!(findings[0].explanation.contains("example:v2"));
+    assert_eq!(
+        findings[0].evidence,
+        vec![
+            "Back-off pulling image \"example:v2\"",
+            "Event: Failed to pull image \"example:v2\": not found (seen 3 times)",
+        ]
+    );
+    assert!(diagnosis::crashed_containers(&pod).is_empty());
+}
+
+#[test]
+fn pending_pod_shows_scheduling_events_and_requests() {
+    let pod = pod(
+        main_container(),
+        json!({
+            "phase": "Pending",
+            "conditions": [{
+                "type": "PodScheduled",
+                "status": "False",
+                "reason": "Unschedulable",
+                "message": "0/3 nodes are available: 3 Insufficient cpu."
+            }]
+        }),
+    );
+    let events = vec![event(
+        "example-web-5d4f-b",
+        "FailedScheduling",
+        "0/3 nodes are available: 3 Insufficient cpu.",
+        1,
+    )];
+
+    let findings = diagnosis::diagnose(&pod, &events, &HashMap::new());
+
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].problem, "Unschedulable");
+    assert_eq!(
+        findings[0].evidence,
+        vec![
+            "0/3 nodes are available: 3 Insufficient cpu.",
+            "Event: 0/3 nodes are available: 3 Insufficient cpu.",
+            "Container main requests cpu 2",
+        ]
+    );
+}
+
+#[test]
+fn failed_probes_show_events_and_probe() {
+    let pod = pod(
+        main_container(),
+        json!({
+            "phase": "Running",
+            "containerStatuses": [{
+                "name": "main",
+                "image": "example:v2",
+                "imageID": "",
+                "ready": true,
+                "restartCount": 0,
+                "state": {"running": {}}
+            }]
+        }),
+    );
+    let events = vec![event(
+        "example-web-5d4f-b",
+        "Unhealthy",
+        "Liveness probe failed: HTTP probe failed with statuscode: 500",
+        2,
+    )];
+
+    let findings = diagnosis::diagnose(&pod, &events, &HashMap::new());
+
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].problem, "LivenessProbeFailed");
+    assert_eq!(
+        findings[0].evidence,
+        vec![
+            "Event: Liveness probe failed: HTTP probe failed with statuscode: 500 (seen 2 times)",
+            "Container main checks HTTP /health on port 3000 every 5s, timing out after 1s",
+        ]
+    );
+}
+
+#[test]
+fn healthy_pod_has_no_findings() {
+    let pod = pod(
+        main_container(),
+        json!({
+            "phase": "Running",
+            "containerStatuses": [{
+                "name": "main",
+                "image": "example:v2",
+                "imageID": "",
+                "ready": true,
+                "restartCount": 0,
+                "state": {"running": {}}
+            }]
+        }),
+    );
+
+    let findings = diagnosis::diagnose(&pod, &[], &HashMap::new());
+
+    assert!(findings.is_empty());
+    assert_eq!(
+        process::render_findings(2, &findings),
+        vec!["All 2 pods look healthy"]
+    );
+}
+
+#[test]
+fn render_groups_findings_by_pod() {
+    let pod = pod(
+        main_container(),
+        json!({
+            "phase": "Running",
+            "containerStatuses": [{
+                "name": "main",
+                "image": "example:v2",
+                "imageID": "",
+                "ready": false,
+                "restartCount": 0,
+                "state": {"running": {}}
+            }]
+        }),
+    );
+
+    let findings = diagnosis::diagnose(&pod, &[], &HashMap::new());
+
+    assert_eq!(
+        process::render_findings(1, &findings),
+        vec![
+            "pod/example-web-5d4f-b",
+            "  NotReady: The pod is running but some containers aren't ready yet.",
+            "    Not ready: main",
+        ]
+    );
+}
+
+#[test]
+fn stuck_pods_are_unschedulable_or_cant_create_containers() {
+    let unschedulable = pod(
+        main_container(),
+        json!({
+            "phase": "Pending",
+            "conditions": [{
+                "type": "PodScheduled",
+                "status": "False",
+                "reason": "Unschedulable"
+            }]
+        }),
+    );
+    let misconfigured = pod(
+        main_container(),
+        json!({
+            "phase": "Pending",
+            "containerStatuses": [{
+                "name": "main",
+                "image": "example:v2",
+                "imageID": "",
+                "ready": false,
+                "restartCount": 0,
+                "state": {"waiting": {
+                    "reason": "CreateContainerConfigError",
+                    "message": "secret \"example\" not found"
+                }}
+            }]
+        }),
+    );
+    let creating = pod(
+        main_container(),
+        json!({
+            "phase": "Pending",
+            "containerStatuses": [{
+                "name": "main",
+                "image": "example:v2",
+                "imageID": "",
+                "ready": false,
+                "restartCount": 0,
+                "state": {"waiting": {"reason": "ContainerCreating"}}
+            }]
+        }),
+    );
+
+    assert_eq!(
+        diagnosis::stuck(&unschedulable).map(|finding| finding.problem),
+        Some(String::from("Unschedulable"))
+    );
+    assert_eq!(
+        diagnosis::stuck(&misconfigured).map(|finding| finding.evidence),
+        Some(vec![String::from("secret \"example\" not found")])
+    );
+    assert!(diagnosis::stuck(&creating).is_none());
+}
+
+#[test]
+fn container_started_waits_until_running_finished_or_stuck() {
+    let started = |state: serde_json::Value| {
+        let pod = pod(
+            main_container(),
+            json!({
+                "phase": "Pending",
+                "containerStatuses": [{
+                    "name": "main",
+                    "image": "example:v2",
+                    "imageID": "",
+                    "ready": false,
+                    "restartCount": 0,
+                    "state": state
+                }]
+            }),
+        );
+        diagnosis::container_started(&pod, "main")
+            .map(|started| started.map_err(|finding| finding.problem))
+    };
+
+    assert_eq!(started(json!({"running": {}})), Some(Ok(true)));
+    assert_eq!(
+        started(json!({"terminated": {"exitCode": 0}})),
+        Some(Ok(false))
+    );
+    assert_eq!(
+        started(json!({"waiting": {"reason": "ImagePullBackOff"}})),
+        Some(Err(String::from("ImagePullBackOff")))
+    );
+    assert_eq!(
+        started(json!({"waiting": {"reason": "ContainerCreating"}})),
+        None
+    );
+}
+
+#[test]
+fn job_finished_uses_job_conditions() {
+    let job = |conditions: serde_json::Value| -> batch::Job {
+        common::resource(json!({
+            "metadata": {"name": "example-migrate"},
+            "status": {"conditions": conditions}
+        }))
+    };
+
+    assert_eq!(diagnosis::job_finished(&job(json!([]))), None);
+    assert_eq!(
+        diagnosis::job_finished(&job(json!([{"type": "Complete", "status": "True"}]))),
+        Some(Ok(()))
+    );
+    assert_eq!(
+        diagnosis::job_finished(&job(json!([
+            {"type": "Failed", "status": "False"},
+            {
+                "type": "Failed",
+                "status": "True",
+                "reason": "BackoffLimitExceeded",
+                "message": "Job has reached the specified backoff limit"
+            }
+        ]))),
+        Some(Err(String::from(
+            "Job has reached the specified backoff limit"
+        )))
+    );
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::{find_release, load_config, load_config_with_local_manifests};
+use flightctl::commands::diff;
+use flightctl::runner::fake::FakeRunner;
+use flightctl::runner::{ExitStatus, Output};
+
+const MANIFESTS: &str = "\
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+";
+
+fn script_diff(runner: &FakeRunner, code: i32) {
+    runner
+        .succeed(
+            "kubectl kustomize git@github.com:example/manifests.git//overlays/staging",
+            MANIFESTS,
+        )
+        .respond(
+            "kubectl --context example-staging diff --server-side --force-conflicts --filename",
+            Output {
+                status: ExitStatus::from_code(code),
+                ..Output::default()
+            },
+        );
+}
+
+#[test]
+fn reports_no_changes() {
+    let config = load_config();
+    let release = find_release(&config, "example-staging");
+    let runner = FakeRunner::new();
+    script_diff(&runner, 0);
+
+    let changed = diff::run(&runner, &config, release).unwrap();
+
+    assert!(!changed);
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn reports_changes() {
+    let config = load_config();
+    let release = find_release(&config, "example-staging");
+    let runner = FakeRunner::new();
+    script_diff(&runner, 1);
+
+    let changed = diff::run(&runner, &config, release).unwrap();
+
+    assert!(changed);
+}
+
+#[test]
+fn fails_when_kubectl_fails() {
+    let config = load_config();
+    let release = find_release(&config, "example-staging");
+    let runner = FakeRunner::new();
+    script_diff(&runner, 2);
+
+    let result = diff::run(&runner, &config, release);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn builds_local_manifests_when_run_from_a_nested_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = load_config_with_local_manifests(dir.path(), &dir.path().join("app/src"));
+    let release = find_release(&config_file.config, "example-staging");
+    let target = dir.path().join("manifests/overlays/staging");
+    let runner = FakeRunner::new();
+    runner
+        .succeed(
+            &format!("kubectl kustomize {}", target.display()),
+            MANIFESTS,
+        )
+        .succeed(
+            "kubectl --context example-staging diff --server-side --force-conflicts --filename",
+            "",
+        );
+
+    let changed = diff::run(&runner, &config_file.config, release).unwrap();
+
+    assert!(!changed);
+    assert!(runner.pending().is_empty());
+}
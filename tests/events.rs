@@ -0,0 +1,152 @@
+mod common;
+
+use flightctl::commands::events;
+use flightctl::events::select;
+use flightctl::workloads::Resources;
+use k8s_openapi::api::core::v1 as k8s;
+use serde_json::json;
+
+fn resources() -> Resources {
+    Resources {
+        deployments: vec![common::resource(json!({
+            "metadata": {"name": "example-web"},
+            "spec": {"selector": {}, "template": {}}
+        }))],
+        replica_sets: vec![common::resource(json!({
+            "metadata": {"name": "example-web-5d4f"},
+            "spec": {"selector": {}}
+        }))],
+        pods: vec![common::resource(json!({
+            "metadata": {"name": "example-web-5d4f-b"}
+        }))],
+        ..Resources::default()
+    }
+}
+
+fn events() -> Vec<k8s::Event> {
+    vec![
+        common::resource(json!({
+            "metadata": {"name": "example-web-5d4f-b.unhealthy"},
+            "involvedObject": {"kind": "Pod", "name": "example-web-5d4f-b"},
+            "type": "Warning",
+            "reason": "Unhealthy",
+            "message": "Readiness probe failed: connection refused\n",
+            "count": 3,
+            "lastTimestamp": "2024-05-01T12:05:00Z"
+        })),
+        common::resource(json!({
+            "metadata": {"name": "other-web.scaled"},
+            "involvedObject": {"kind": "Deployment", "name": "other-web"},
+            "type": "Normal",
+            "reason": "ScalingReplicaSet",
+            "message": "Scaled up replica set other-web-7c9 to 1",
+            "lastTimestamp": "2024-05-01T12:00:00Z"
+        })),
+        common::resource(json!({
+            "metadata": {"name": "example-web.scaled"},
+            "involvedObject": {"kind": "Deployment", "name": "example-web"},
+            "type": "Normal",
+            "reason": "ScalingReplicaSet",
+            "message": "Scaled up replica set example-web-5d4f to 1",
+            "eventTime": "2024-05-01T12:00:00.000000Z"
+        })),
+        common::resource(json!({
+            "metadata": {"name": "example-web-5d4f.created"},
+            "involvedObject": {"kind": "ReplicaSet", "name": "example-web-5d4f"},
+            "type": "Normal",
+            "reason": "SuccessfulCreate",
+            "message": "Created pod: example-web-5d4f-b",
+            "count": 1,
+            "firstTimestamp": "2024-05-01T12:00:01Z"
+        })),
+        common::resource(json!({
+            "metadata": {"name": "example-web-5d4f.pod"},
+            "involvedObject": {"kind": "ReplicaSet", "name": "example-web-5d4f-b"},
+            "type": "Warning",
+            "reason": "Mismatched",
+            "message": "A replica set which happens to share a pod's name"
+        })),
+    ]
+}
+
+#[test]
+fn selects_events_for_owned_objects_by_time() {
+    let records = select(events(), &resources(), false);
+
+    let summary: Vec<(&str, &str, i32)> = records
+        .iter()
+        .map(|record| (record.object.as_str(), record.reason.as_str(), record.count))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("deployment/example-web", "ScalingReplicaSet", 1),
+            ("replicaset/example-web-5d4f", "SuccessfulCreate", 1),
+            ("pod/example-web-5d4f-b", "Unhealthy", 3),
+        ]
+    );
+    assert_eq!(
+        records[2].message,
+        "Readiness probe failed: connection refused"
+    );
+}
+
+#[test]
+fn selects_only_warnings() {
+    let records = select(events(), &resources(), true);
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].reason, "Unhealthy");
+    assert!(records[0].is_warning());
+}
+
+#[test]
+fn row_shows_repeated_events() {
+    let records = select(events(), &resources(), true);
+
+    let row = events::row(&records[0]);
+
+    assert_eq!(
+        row[1..],
+        [
+            "Warning",
+            "Unhealthy",
+            "pod/example-web-5d4f-b (x3)",
+            "Readiness probe failed: connection refused",
+        ]
+    );
+}
+
+#[test]
+fn selects_events_for_replaced_objects_by_name() {
+    let event = |kind: &str, name: &str| -> k8s::Event {
+        common::resource(json!({
+            "metadata": {"name": format!("{}.event", name)},
+            "involvedObject": {"kind": kind, "name": name},
+            "reason": "Killing"
+        }))
+    };
+    let events = vec![
+        event("Pod", "example-web-7a8b-c"),
+        event("ReplicaSet", "example-web-7a8b"),
+        event("Pod", "example-web-5d4f-x"),
+        event("Pod", "example-web-worker-9c0d-e"),
+        event("ReplicaSet", "example-web-worker-9c0d"),
+        event("Deployment", "example-web-old"),
+    ];
+
+    let records = select(events, &resources(), false);
+
+    let objects: Vec<&str> = records
+        .iter()
+        .map(|record| record.object.as_str())
+        .collect();
+    assert_eq!(
+        objects,
+        vec![
+            "pod/example-web-5d4f-x",
+            "pod/example-web-7a8b-c",
+            "replicaset/example-web-7a8b",
+        ]
+    );
+}
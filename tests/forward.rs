@@ -0,0 +1,97 @@
+mod common;
+
+use common::{find_release, load_config};
+use flightctl::commands::forward::{self, Forwarder, Target};
+use flightctl::kubeclient::{self, Selector};
+use flightctl::runner::fake::FakeRunner;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+fn names(forwards: &[(&str, &flightctl::Forward)]) -> Vec<String> {
+    forwards.iter().map(|(name, _)| name.to_string()).collect()
+}
+
+#[test]
+fn finds_all_or_named_forwards() {
+    let config = load_config();
+    let application = &config.applications[0];
+
+    let all = application.find_forwards(&[]).unwrap();
+    assert_eq!(names(&all), vec!["metrics", "web"]);
+
+    let named = application.find_forwards(&[String::from("web")]).unwrap();
+    assert_eq!(names(&named), vec!["web"]);
+    assert_eq!(named[0].1.local_url(), "http://localhost:3000");
+    assert_eq!(all[0].1.local_url(), "localhost:9394");
+
+    let error = application
+        .find_forwards(&[String::from("db")])
+        .unwrap_err();
+    assert!(error.to_string().contains("metrics, web"));
+}
+
+#[test]
+fn targets_services_or_pods_matching_both_selectors() {
+    let config = load_config();
+    let application = &config.applications[0];
+    let forwards = application.find_forwards(&[]).unwrap();
+    let selector = || {
+        Selector::new(HashMap::from([(
+            String::from("app.kubernetes.io/name"),
+            String::from("example"),
+        )]))
+    };
+
+    match forward::target(forwards[1].1, selector()) {
+        Target::Service(name) => assert_eq!(name, "example-web"),
+        target => panic!("Expected a service, got {:?}", target),
+    }
+    match forward::target(forwards[0].1, selector()) {
+        Target::Pod(selector) => {
+            let mut labels = BTreeMap::from([(
+                String::from("app.kubernetes.io/name"),
+                String::from("example"),
+            )]);
+            assert!(!selector.matches(Some(&labels)));
+            labels.insert(
+                String::from("app.kubernetes.io/component"),
+                String::from("worker"),
+            );
+            assert!(selector.matches(Some(&labels)));
+        }
+        target => panic!("Expected a pod, got {:?}", target),
+    }
+}
+
+#[test]
+fn reconnects_forwards_which_disconnect() {
+    let config = load_config();
+    let release = find_release(&config, "example-staging");
+    let runner = FakeRunner::new();
+    runner
+        .spawn_running("kubectl --context example-staging port-forward service/api 8000:80")
+        .fail(
+            "kubectl --context example-staging port-forward service/web 3000:80",
+            1,
+            "lost connection to pod",
+        )
+        .spawn_running("kubectl --context example-staging port-forward service/web 3000:80");
+    let client = kubeclient::new(&runner, &release.context);
+
+    let mut forwarder = Forwarder::new(&client, Duration::ZERO);
+    forwarder.add("api", Target::Service(String::from("api")), 8000, 80);
+    forwarder.add("web", Target::Service(String::from("web")), 3000, 80);
+    forwarder.poll().unwrap();
+    forwarder.poll().unwrap();
+    forwarder.poll().unwrap();
+
+    assert_eq!(
+        runner.calls(),
+        vec![
+            "kubectl --context example-staging port-forward service/api 8000:80",
+            "kubectl --context example-staging port-forward service/web 3000:80",
+            "kubectl --context example-staging port-forward service/web 3000:80",
+        ]
+    );
+    assert!(runner.pending().is_empty());
+}
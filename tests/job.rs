@@ -0,0 +1,86 @@
+use flightctl::commands::job::{self, RunOptions};
+use flightctl::kubeclient;
+use flightctl::podspec;
+use flightctl::runner::fake::FakeRunner;
+use k8s_openapi::api::core::v1 as k8s;
+
+fn template() -> k8s::Pod {
+    k8s::Pod {
+        spec: Some(k8s::PodSpec {
+            containers: vec![k8s::Container {
+                name: String::from("main"),
+                image: Some(String::from("example:abc123")),
+                ..k8s::Container::default()
+            }],
+            ..k8s::PodSpec::default()
+        }),
+        ..k8s::Pod::default()
+    }
+}
+
+fn command() -> Vec<String> {
+    vec![String::from("rake"), String::from("db:migrate")]
+}
+
+#[test]
+fn builds_job_which_runs_once() {
+    let job = podspec::job(template(), "example-web", "main", &command(), 600).unwrap();
+
+    assert_eq!(
+        job.metadata.generate_name.as_deref(),
+        Some("example-web-run-")
+    );
+    let spec = job.spec.unwrap();
+    assert_eq!(spec.backoff_limit, Some(0));
+    assert_eq!(spec.active_deadline_seconds, Some(600));
+    let pod = spec.template.spec.unwrap();
+    assert_eq!(pod.restart_policy.as_deref(), Some("Never"));
+    assert_eq!(pod.containers[0].command, Some(command()));
+    assert_eq!(pod.containers[0].tty, Some(false));
+}
+
+#[test]
+fn reads_exit_code_of_terminated_container() {
+    let mut pod = template();
+    assert_eq!(podspec::exit_code(&pod, "main"), None);
+
+    pod.status = Some(k8s::PodStatus {
+        container_statuses: Some(vec![k8s::ContainerStatus {
+            name: String::from("main"),
+            state: Some(k8s::ContainerState {
+                terminated: Some(k8s::ContainerStateTerminated {
+                    exit_code: 3,
+                    ..k8s::ContainerStateTerminated::default()
+                }),
+                ..k8s::ContainerState::default()
+            }),
+            ..k8s::ContainerStatus::default()
+        }]),
+        ..k8s::PodStatus::default()
+    });
+
+    assert_eq!(podspec::exit_code(&pod, "main"), Some(3));
+}
+
+#[test]
+fn detached_jobs_return_after_creation() {
+    let runner = FakeRunner::new();
+    runner.succeed(
+        "kubectl --context example-staging create --filename",
+        "job.batch/example-web-run-x7k2q\n",
+    );
+    let client = kubeclient::new(&runner, "example-staging");
+    let job = podspec::job(template(), "example-web", "main", &command(), 600).unwrap();
+    let options = RunOptions {
+        job: false,
+        detach: true,
+        script: None,
+        timeout: Some(600),
+    };
+
+    let code = job::run_job(&client, &job, "main", &options).unwrap();
+
+    assert_eq!(code, 0);
+    assert!(runner.pending().is_empty());
+    assert_eq!(runner.calls().len(), 1);
+}
@@ -0,0 +1,77 @@
+use flightctl::kubeenv::{expand, resource_value};
+use k8s_openapi::api::core::v1::ResourceRequirements;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use std::collections::BTreeMap;
+
+fn lookup(name: &str) -> Option<String> {
+    match name {
+        "HOST" => Some(String::from("db.internal")),
+        "PORT" => Some(String::from("5432")),
+        _ => None,
+    }
+}
+
+fn resources(limits: &[(&str, &str)], requests: &[(&str, &str)]) -> ResourceRequirements {
+    let quantities = |pairs: &[(&str, &str)]| -> Option<BTreeMap<String, Quantity>> {
+        Some(
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), Quantity(value.to_string())))
+                .collect(),
+        )
+    };
+    ResourceRequirements {
+        limits: quantities(limits),
+        requests: quantities(requests),
+    }
+}
+
+#[test]
+fn expands_references_to_defined_variables() {
+    assert_eq!(
+        expand("postgres://$(HOST):$(PORT)/app", lookup),
+        "postgres://db.internal:5432/app"
+    );
+}
+
+#[test]
+fn leaves_unknown_references_and_unescapes_dollars() {
+    assert_eq!(
+        expand("$(MISSING)/$$(HOST)/$5/$(", lookup),
+        "$(MISSING)/$(HOST)/$5/$("
+    );
+}
+
+#[test]
+fn rounds_resources_up_to_the_divisor() {
+    let resources = resources(&[("cpu", "1500m"), ("memory", "1Gi")], &[("cpu", "250m")]);
+    let mi = Quantity(String::from("1Mi"));
+    let milli = Quantity(String::from("1m"));
+
+    assert_eq!(
+        resource_value(Some(&resources), "limits.cpu", None).as_deref(),
+        Some("2")
+    );
+    assert_eq!(
+        resource_value(Some(&resources), "requests.cpu", Some(&milli)).as_deref(),
+        Some("250")
+    );
+    assert_eq!(
+        resource_value(Some(&resources), "limits.memory", Some(&mi)).as_deref(),
+        Some("1024")
+    );
+}
+
+#[test]
+fn reports_unset_requests_as_zero_and_unset_limits_as_unknown() {
+    let resources = resources(&[], &[]);
+
+    assert_eq!(
+        resource_value(Some(&resources), "requests.memory", None).as_deref(),
+        Some("0")
+    );
+    assert_eq!(
+        resource_value(Some(&resources), "limits.memory", None),
+        None
+    );
+}
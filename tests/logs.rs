@@ -0,0 +1,80 @@
+mod common;
+
+use flightctl::commands::logs::{log_targets, parse_duration};
+use k8s_openapi::api::core::v1 as k8s;
+use serde_json::json;
+
+fn pod(name: &str, phase: &str) -> k8s::Pod {
+    common::resource(json!({
+        "metadata": {"name": name},
+        "spec": {"containers": [{"name": "main"}, {"name": "sidecar"}]},
+        "status": {"phase": phase}
+    }))
+}
+
+fn targets(pods: &[k8s::Pod], container: Option<&str>) -> Vec<(String, String)> {
+    log_targets(pods, container)
+        .into_iter()
+        .map(|target| (target.pod, target.container))
+        .collect()
+}
+
+#[test]
+fn parses_durations() {
+    assert_eq!(parse_duration("30s"), Ok(30));
+    assert_eq!(parse_duration("5m"), Ok(300));
+    assert_eq!(parse_duration("1h30m"), Ok(5400));
+}
+
+#[test]
+fn rejects_invalid_durations() {
+    assert!(parse_duration("").is_err());
+    assert!(parse_duration("30").is_err());
+    assert!(parse_duration("h").is_err());
+    assert_eq!(
+        parse_duration("2d"),
+        Err(String::from("Unknown unit 'd' in duration 2d"))
+    );
+}
+
+#[test]
+fn targets_every_container_of_running_pods() {
+    let pods = vec![
+        pod("example-web-a", "Running"),
+        pod("example-web-b", "Pending"),
+    ];
+
+    assert_eq!(
+        targets(&pods, None),
+        vec![
+            (String::from("example-web-a"), String::from("main")),
+            (String::from("example-web-a"), String::from("sidecar")),
+        ]
+    );
+}
+
+#[test]
+fn targets_only_the_given_container() {
+    let pods = vec![
+        pod("example-web-a", "Running"),
+        pod("example-web-b", "Running"),
+    ];
+
+    assert_eq!(
+        targets(&pods, Some("main")),
+        vec![
+            (String::from("example-web-a"), String::from("main")),
+            (String::from("example-web-b"), String::from("main")),
+        ]
+    );
+}
+
+#[test]
+fn skips_pods_without_status() {
+    let pod: k8s::Pod = common::resource(json!({
+        "metadata": {"name": "example-web-a"},
+        "spec": {"containers": [{"name": "main"}]}
+    }));
+
+    assert!(targets(&[pod], None).is_empty());
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use flightctl::kubeclient::Selector;
+use flightctl::podspec;
+use k8s_openapi::api::apps::v1 as apps;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+
+fn selector(labels: &[(&str, &str)]) -> Selector {
+    Selector::new(
+        labels
+            .iter()
+            .map(|(key, value)| (String::from(*key), String::from(*value)))
+            .collect::<HashMap<String, String>>(),
+    )
+}
+
+fn labels(labels: &[(&str, &str)]) -> BTreeMap<String, String> {
+    labels
+        .iter()
+        .map(|(key, value)| (String::from(*key), String::from(*value)))
+        .collect()
+}
+
+fn deployment() -> apps::Deployment {
+    common::resource(json!({
+        "metadata": {"name": "example-web", "namespace": "example-staging"},
+        "spec": {
+            "selector": {},
+            "template": {
+                "metadata": {"labels": {"app.kubernetes.io/name": "example", "role": "web"}},
+                "spec": {"containers": [{"name": "main", "image": "example:abc123"}]}
+            }
+        }
+    }))
+}
+
+#[test]
+fn matches_labels_containing_every_selected_label() {
+    let web = selector(&[("app.kubernetes.io/name", "example"), ("role", "web")]);
+
+    assert!(web.matches(Some(&labels(&[
+        ("app.kubernetes.io/name", "example"),
+        ("role", "web"),
+        ("pod-template-hash", "5d4f"),
+    ]))));
+    assert!(!web.matches(Some(&labels(&[("app.kubernetes.io/name", "example")]))));
+    assert!(!web.matches(Some(&labels(&[
+        ("app.kubernetes.io/name", "example"),
+        ("role", "worker"),
+    ]))));
+    assert!(!web.matches(None));
+}
+
+#[test]
+fn empty_selector_matches_anything() {
+    let everything = selector(&[]);
+
+    assert!(everything.matches(None));
+    assert!(everything.matches(Some(&labels(&[("role", "web")]))));
+}
+
+#[test]
+fn builds_pod_from_deployment_template() {
+    let (name, pod) = podspec::template_pod(deployment());
+
+    assert_eq!(name, "example-web");
+    assert_eq!(pod.metadata.name, None);
+    assert_eq!(pod.metadata.namespace.as_deref(), Some("example-staging"));
+    assert!(selector(&[("role", "web")]).matches(pod.metadata.labels.as_ref()));
+    assert_eq!(pod.spec.unwrap().containers[0].name, "main");
+    assert!(pod.status.is_none());
+}
+
+#[test]
+fn builds_pod_from_deployment_without_template() {
+    let deployment: apps::Deployment = common::resource(json!({
+        "metadata": {"name": "example-web"}
+    }));
+
+    let (name, pod) = podspec::template_pod(deployment);
+
+    assert_eq!(name, "example-web");
+    assert!(pod.spec.is_none());
+}
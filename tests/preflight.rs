@@ -0,0 +1,161 @@
+mod common;
+
+use common::{environment, load_config};
+use flightctl::preflight;
+use flightctl::runner::fake::FakeRunner;
+
+const SANDBOX_CLUSTER: &str = "endpoint: https://sandbox.eks.amazonaws.com\ncert: c2FuZGJveA==\n";
+const PRODUCTION_CLUSTER: &str =
+    "endpoint: https://production.eks.amazonaws.com\ncert: cHJvZHVjdGlvbg==\n";
+
+#[test]
+fn reuses_existing_profile_and_kubeconfig() {
+    let config = load_config();
+    let runner = FakeRunner::new();
+    runner
+        .succeed("aws configure list-profiles", "default\nexample-sandbox\n")
+        .succeed("aws --profile example-sandbox sts get-caller-identity", "")
+        .succeed(
+            "aws --profile example-sandbox --region us-east-1 eks describe-cluster --name sandbox",
+            SANDBOX_CLUSTER,
+        );
+
+    let release = preflight::run(&runner, &config, environment("staging")).unwrap();
+
+    assert_eq!(release.name, "example-staging");
+    assert!(runner.pending().is_empty());
+    assert!(runner
+        .calls()
+        .iter()
+        .all(|call| !call.starts_with("kubectl")));
+}
+
+#[test]
+fn creates_missing_aws_profile() {
+    let config = load_config();
+    let runner = FakeRunner::new();
+    runner
+        .succeed("aws configure list-profiles", "default\n")
+        .succeed(
+            "aws --profile example-sandbox configure set region us-east-1",
+            "",
+        )
+        .succeed(
+            "aws --profile example-sandbox configure set sso_account_id 111111111111",
+            "",
+        )
+        .succeed("aws --profile example-sandbox sts get-caller-identity", "")
+        .succeed(
+            "aws --profile example-sandbox --region us-east-1 eks describe-cluster --name sandbox",
+            SANDBOX_CLUSTER,
+        );
+
+    preflight::run(&runner, &config, environment("staging")).unwrap();
+
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn logs_in_when_session_has_expired() {
+    let config = load_config();
+    let runner = FakeRunner::new();
+    runner
+        .succeed("aws configure list-profiles", "example-sandbox\n")
+        .fail(
+            "aws --profile example-sandbox sts get-caller-identity",
+            255,
+            "The SSO session has expired",
+        )
+        .succeed("aws --profile example-sandbox sso login", "")
+        .succeed("aws --profile example-sandbox sts get-caller-identity", "")
+        .succeed(
+            "aws --profile example-sandbox --region us-east-1 eks describe-cluster --name sandbox",
+            SANDBOX_CLUSTER,
+        );
+
+    preflight::run(&runner, &config, environment("staging")).unwrap();
+
+    assert_eq!(
+        runner.calls()[1..4],
+        [
+            "aws --profile example-sandbox sts get-caller-identity",
+            "aws --profile example-sandbox sso login",
+            "aws --profile example-sandbox sts get-caller-identity",
+        ]
+    );
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn fails_when_login_fails() {
+    let config = load_config();
+    let runner = FakeRunner::new();
+    runner
+        .succeed("aws configure list-profiles", "example-sandbox\n")
+        .fail(
+            "aws --profile example-sandbox sts get-caller-identity",
+            255,
+            "The SSO session has expired",
+        )
+        .fail("aws --profile example-sandbox sso login", 1, "");
+
+    let result = preflight::run(&runner, &config, environment("staging"));
+
+    assert!(result.is_err());
+    assert!(runner
+        .calls()
+        .iter()
+        .all(|call| !call.contains("describe-cluster")));
+}
+
+#[test]
+fn writes_missing_kubeconfig_entries() {
+    let config = load_config();
+    let runner = FakeRunner::new();
+    runner
+        .succeed("aws configure list-profiles", "example-production\n")
+        .succeed("aws --profile example-production sts get-caller-identity", "")
+        .succeed(
+            "aws --profile example-production --region us-east-1 eks describe-cluster --name production",
+            PRODUCTION_CLUSTER,
+        )
+        .succeed(
+            "kubectl config set-credentials example-production \
+             --exec-command aws \
+             --exec-api-version client.authentication.k8s.io/v1beta1 \
+             --exec-arg --region --exec-arg us-east-1 \
+             --exec-arg eks --exec-arg get-token \
+             --exec-arg --cluster-name --exec-arg production \
+             --exec-env AWS_PROFILE=example-production",
+            "",
+        )
+        .succeed(
+            "kubectl config set-cluster example-production \
+             --server https://production.eks.amazonaws.com \
+             --embed-certs --certificate-authority",
+            "",
+        )
+        .succeed(
+            "kubectl config set-context example-production \
+             --cluster example-production \
+             --user example-production \
+             --namespace example-production",
+            "",
+        );
+
+    let release = preflight::run(&runner, &config, environment("production")).unwrap();
+
+    assert_eq!(release.name, "example-production");
+    assert!(runner.pending().is_empty());
+}
+
+#[test]
+fn fails_for_unknown_environment() {
+    let config = load_config();
+    let runner = FakeRunner::new();
+
+    let result = preflight::run(&runner, &config, environment("qa"));
+
+    assert!(result.is_err());
+    assert!(runner.calls().is_empty());
+}
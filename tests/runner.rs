@@ -0,0 +1,29 @@
+use flightctl::runner::{CommandRunner, SystemRunner};
+
+#[cfg(unix)]
+#[test]
+fn streams_input_to_programs() {
+    let input = "line\n".repeat(100_000);
+    let mut reader = input.as_bytes();
+
+    let output = SystemRunner
+        .output_with_input("cat", &[], &mut reader)
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, input.as_bytes());
+}
+
+#[cfg(unix)]
+#[test]
+fn streams_output_from_programs() {
+    let mut output = Vec::new();
+
+    let result = SystemRunner
+        .output_to("sh", &["-c", "echo hello; echo oops >&2"], &mut output)
+        .unwrap();
+
+    assert!(result.status.success());
+    assert_eq!(output, b"hello\n");
+    assert_eq!(result.stderr, b"oops\n");
+}
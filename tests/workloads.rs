@@ -0,0 +1,299 @@
+mod common;
+
+use flightctl::commands::process::{self, ChangeTracker};
+use flightctl::workloads::{self, Change, Resources};
+use k8s_openapi::api::core::v1 as k8s;
+use kube::api::WatchEvent;
+use serde_json::json;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+fn owned_by(kind: &str, name: &str) -> serde_json::Value {
+    json!([{"apiVersion": "apps/v1", "kind": kind, "name": name, "uid": name}])
+}
+
+fn resources() -> Resources {
+    Resources {
+        deployments: vec![common::resource(json!({
+            "metadata": {"name": "example-web"},
+            "spec": {"replicas": 2, "selector": {}, "template": {}},
+            "status": {"readyReplicas": 1, "updatedReplicas": 2, "availableReplicas": 1}
+        }))],
+        stateful_sets: vec![],
+        cron_jobs: vec![common::resource(json!({
+            "metadata": {"name": "example-cleanup"},
+            "spec": {"schedule": "0 * * * *", "jobTemplate": {}},
+            "status": {"active": [{"name": "example-cleanup-28000000"}]}
+        }))],
+        replica_sets: vec![common::resource(json!({
+            "metadata": {
+                "name": "example-web-5d4f",
+                "ownerReferences": owned_by("Deployment", "example-web")
+            },
+            "spec": {"selector": {}}
+        }))],
+        jobs: vec![common::resource(json!({
+            "metadata": {
+                "name": "example-cleanup-28000000",
+                "ownerReferences": owned_by("CronJob", "example-cleanup")
+            },
+            "spec": {"template": {}}
+        }))],
+        pods: vec![
+            common::resource(json!({
+                "metadata": {
+                    "name": "example-web-5d4f-b",
+                    "ownerReferences": owned_by("ReplicaSet", "example-web-5d4f")
+                },
+                "spec": {
+                    "nodeName": "node-2",
+                    "containers": [
+                        {"name": "main", "image": "registry.example.com:5000/example:v1.2.3"},
+                        {"name": "proxy", "image": "envoyproxy/envoy"}
+                    ]
+                },
+                "status": {
+                    "phase": "Running",
+                    "containerStatuses": [
+                        {
+                            "name": "main", "image": "", "imageID": "", "ready": false,
+                            "restartCount": 4,
+                            "state": {"waiting": {"reason": "CrashLoopBackOff"}},
+                            "lastState": {"terminated": {"reason": "OOMKilled", "exitCode": 137}}
+                        },
+                        {
+                            "name": "proxy", "image": "", "imageID": "", "ready": true,
+                            "restartCount": 1,
+                            "state": {"running": {}}
+                        }
+                    ]
+                }
+            })),
+            common::resource(json!({
+                "metadata": {
+                    "name": "example-web-5d4f-a",
+                    "ownerReferences": owned_by("ReplicaSet", "example-web-5d4f")
+                },
+                "spec": {
+                    "nodeName": "node-1",
+                    "containers": [
+                        {"name": "main", "image": "example@sha256:0123456789abcdef0123"}
+                    ]
+                },
+                "status": {
+                    "phase": "Running",
+                    "containerStatuses": [{
+                        "name": "main", "image": "", "imageID": "", "ready": true,
+                        "restartCount": 0, "state": {"running": {}}
+                    }]
+                }
+            })),
+            common::resource(json!({
+                "metadata": {
+                    "name": "example-cleanup-28000000-x",
+                    "ownerReferences": owned_by("Job", "example-cleanup-28000000")
+                },
+                "spec": {"containers": [{"name": "main", "image": "example:v1.2.3"}]},
+                "status": {"phase": "Pending"}
+            })),
+            common::resource(json!({
+                "metadata": {"name": "example-debug"},
+                "spec": {"containers": [{"name": "main", "image": "busybox:1.36"}]},
+                "status": {"phase": "Succeeded"}
+            })),
+        ],
+    }
+}
+
+#[test]
+fn groups_pods_under_their_workloads() {
+    let workloads = workloads::group(resources());
+
+    let groups: Vec<(&str, &str, Vec<&str>)> = workloads
+        .iter()
+        .map(|workload| {
+            (
+                workload.kind.as_str(),
+                workload.name.as_str(),
+                workload.pods.iter().map(|pod| pod.name.as_str()).collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        groups,
+        vec![
+            (
+                "Deployment",
+                "example-web",
+                vec!["example-web-5d4f-a", "example-web-5d4f-b"]
+            ),
+            (
+                "CronJob",
+                "example-cleanup",
+                vec!["example-cleanup-28000000-x"]
+            ),
+            ("Pod", "example-debug", vec!["example-debug"]),
+        ]
+    );
+
+    let crashing = &workloads[0].pods[1];
+    assert_eq!(crashing.status, "CrashLoopBackOff");
+    assert_eq!(crashing.restarts, 5);
+    assert_eq!((crashing.ready, crashing.containers), (1, 2));
+    let termination = crashing.last_termination.as_ref().unwrap();
+    assert_eq!(
+        (termination.reason.as_str(), termination.exit_code),
+        ("OOMKilled", 137)
+    );
+}
+
+#[test]
+fn renders_workloads_with_pod_tables() {
+    let lines = process::render(&workloads::group(resources()), &HashSet::new());
+
+    assert_eq!(
+        lines[0],
+        "deployment/example-web   1/2 ready, 2 up-to-date, 1 available, age <unknown>"
+    );
+    assert!(lines[1].starts_with("  POD "));
+    assert!(lines[2].starts_with("  example-web-5d4f-a "));
+    assert!(lines[2].contains(" @0123456789ab "));
+    assert!(lines[3].contains(" CrashLoopBackOff "));
+    assert!(lines[3].contains(" node-2 "));
+    assert!(lines[3].contains(" v1.2.3,latest "));
+    assert!(lines[3].ends_with(" OOMKilled (exit 137) <unknown> ago"));
+    assert_eq!(lines[4], "");
+    assert_eq!(
+        lines[5],
+        "cronjob/example-cleanup   1 active, schedule 0 * * * *, age <unknown>"
+    );
+}
+
+#[test]
+fn shortens_images_to_tags() {
+    assert_eq!(workloads::image_tag("example:v1"), "v1");
+    assert_eq!(workloads::image_tag("registry:5000/example"), "latest");
+    assert_eq!(workloads::image_tag("example@sha256:abc"), "@abc");
+}
+
+#[test]
+fn applies_watch_events() {
+    let mut resources = resources();
+    let mut pod: k8s::Pod = common::resource(json!({
+        "metadata": {"name": "example-debug"},
+        "status": {"phase": "Failed"}
+    }));
+    resources
+        .apply(Change::Pod(Box::new(WatchEvent::Modified(pod.clone()))))
+        .unwrap();
+    pod.metadata.name = Some(String::from("example-console"));
+    resources
+        .apply(Change::Pod(Box::new(WatchEvent::Added(pod))))
+        .unwrap();
+    let deleted = resources.pods[0].clone();
+    resources
+        .apply(Change::Pod(Box::new(WatchEvent::Deleted(deleted))))
+        .unwrap();
+
+    let pods: Vec<(&str, Option<&str>)> = resources
+        .pods
+        .iter()
+        .map(|pod| {
+            (
+                pod.metadata.name.as_deref().unwrap(),
+                pod.status
+                    .as_ref()
+                    .and_then(|status| status.phase.as_deref()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        pods,
+        vec![
+            ("example-web-5d4f-a", Some("Running")),
+            ("example-cleanup-28000000-x", Some("Pending")),
+            ("example-debug", Some("Failed")),
+            ("example-console", Some("Failed")),
+        ]
+    );
+}
+
+#[test]
+fn highlights_pods_which_change_state() {
+    let mut tracker = ChangeTracker::default();
+    let start = Instant::now();
+    let mut resources = resources();
+    assert!(tracker
+        .update(&workloads::group(resources.clone()), start)
+        .is_empty());
+
+    let mut pod = resources.pods[2].clone();
+    pod.status.as_mut().unwrap().phase = Some(String::from("Running"));
+    resources
+        .apply(Change::Pod(Box::new(WatchEvent::Modified(pod))))
+        .unwrap();
+    let workloads = workloads::group(resources.clone());
+    let highlighted = tracker.update(&workloads, start + Duration::from_secs(1));
+    assert_eq!(
+        highlighted,
+        HashSet::from([String::from("example-cleanup-28000000-x")])
+    );
+
+    let lines = process::render(&workloads, &highlighted);
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("* example-cleanup-28000000-x ")));
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("  example-web-5d4f-a ")));
+
+    let later = tracker.update(&workloads, start + Duration::from_secs(60));
+    assert!(later.is_empty());
+}
+
+#[test]
+fn does_not_highlight_pods_first_seen_when_resyncing() {
+    let mut tracker = ChangeTracker::default();
+    let start = Instant::now();
+    let mut resources = resources();
+
+    // A partial list, such as one missing pods, followed by a complete one.
+    let partial = Resources {
+        pods: vec![],
+        ..resources.clone()
+    };
+    assert!(tracker.resync(&workloads::group(partial), start).is_empty());
+    assert!(tracker
+        .resync(&workloads::group(resources.clone()), start)
+        .is_empty());
+
+    // Pods which changed while the watch was closed are still highlighted.
+    let mut pod = resources.pods[2].clone();
+    pod.status.as_mut().unwrap().phase = Some(String::from("Running"));
+    resources
+        .apply(Change::Pod(Box::new(WatchEvent::Modified(pod))))
+        .unwrap();
+    let highlighted = tracker.resync(
+        &workloads::group(resources.clone()),
+        start + Duration::from_secs(1),
+    );
+    assert_eq!(
+        highlighted,
+        HashSet::from([String::from("example-cleanup-28000000-x")])
+    );
+
+    // Pods which appear while watching are new, so they're highlighted.
+    let mut pod = resources.pods[0].clone();
+    pod.metadata.name = Some(String::from("example-web-5d4f-c"));
+    resources
+        .apply(Change::Pod(Box::new(WatchEvent::Added(pod))))
+        .unwrap();
+    let highlighted = tracker.update(
+        &workloads::group(resources.clone()),
+        start + Duration::from_secs(30),
+    );
+    assert_eq!(
+        highlighted,
+        HashSet::from([String::from("example-web-5d4f-c")])
+    );
+}